@@ -1,21 +1,35 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal256, Deps, DepsMut, Env,
-    MessageInfo, Order, Response, StdError, StdResult, Uint128, Uint256,
+    entry_point, from_binary, to_binary, Addr, BankMsg, Binary, BlockInfo, Coin, CosmosMsg, Deps,
+    DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Timestamp, Uint128, Uint256,
+    WasmMsg,
 };
 use cw0::maybe_addr;
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, Denom};
+use cw_controllers::ClaimsResponse;
 use cw_storage_plus::Bound;
-use cw_utils::must_pay;
+use cw_utils::{must_pay, Expiration, PaymentError};
 
+use crate::math::{accrual, floor_with_remainder, index_delta, pro_rata, scale};
+use crate::migrations::migrate_v0_1;
 use crate::msg::{
-    AccruedRewardsResponse, ConfigResponse, ExecuteMsg, HolderResponse, HoldersResponse,
-    InstantiateMsg, MigrateMsg, QueryMsg, StateResponse,
+    AccruedReward, AccruedRewardsResponse, Asset, AssetSolvency, ConfigResponse, ContractCall,
+    ExecuteMsg, HolderResponse, HoldersResponse, InfoResponse, InstantiateMsg, MigrateMsg,
+    Operator, OperatorsResponse, QueryMsg, ReceiveMsg, RewardDenomsResponse,
+    RewardRecipientResponse, SolvencyResponse, StakedBalanceAtHeightResponse, StateResponse,
+    TotalPowerAtHeightResponse, TotalStakedAtHeightResponse, VotingPowerAtHeightResponse,
+};
+use crate::state::{remove_holder, save_holder, save_state, DAO, STAKED_BALANCES, STAKED_TOTAL};
+use crate::state::{
+    Config, Holder, Operation, PendingAdmin, RewardState, State, CLAIMS, CONFIG, HOLDERS,
+    MAX_REWARD_DURATION, OPERATORS, PENDING_ADMIN, REWARD_RECIPIENTS, STATE,
 };
-use crate::state::{Config, Holder, State, CONFIG, HOLDERS, STATE};
 use crate::ContractError;
 
-use std::convert::TryInto;
-use std::ops::Add;
-use std::str::FromStr;
+use semver::Version;
+
+// version info for migration
+const CONTRACT_NAME: &str = "crates.io:cw-share";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -30,33 +44,71 @@ pub fn instantiate(
         None => info.sender.clone(),
     };
 
-    //check if staked token denom is same as reward denom
-    if msg.staked_token_denom == msg.reward_denom {
-        return Err(ContractError::SameDenom {});
+    //check if staked and reward tokens are valid cw20 contract addresses
+    let staked_token_denom = validate_denom(deps.as_ref(), msg.staked_token_denom)?;
+    let mut rewards: Vec<RewardState> = vec![];
+    for reward_denom in msg.reward_denoms {
+        let reward_denom = validate_denom(deps.as_ref(), reward_denom)?;
+
+        //check if staked token denom is same as reward denom
+        if staked_token_denom == reward_denom && !msg.allow_compounding {
+            return Err(ContractError::SameDenom {});
+        }
+        if rewards.iter().any(|reward| reward.denom == reward_denom) {
+            return Err(ContractError::DuplicateDenom {});
+        }
+        rewards.push(RewardState::new(reward_denom));
+    }
+
+    if matches!(msg.reward_duration, Some(duration) if duration == 0 || duration > MAX_REWARD_DURATION)
+    {
+        return Err(ContractError::InvalidRewardDuration {});
     }
+    let pauser = maybe_addr(deps.api, msg.pauser)?;
 
     let config: Config = Config {
-        staked_token_denom: msg.staked_token_denom,
-        reward_denom: msg.reward_denom,
-        admin: admin.clone(),
+        staked_token_denom,
+        admin: Some(admin.clone()),
+        unbonding_period: msg.unbonding_period,
+        reward_duration: msg.reward_duration,
+        pauser,
+        paused: vec![],
+        allow_compounding: msg.allow_compounding,
+        transfers_enabled: msg.transfers_enabled,
     };
 
     let state = State {
-        global_index: Decimal256::zero(),
         total_staked: Uint128::zero(),
-        total_rewards: Uint128::zero(),
-        rewards_claimed: Uint128::zero(),
+        total_unbonding: Uint128::zero(),
+        rewards,
+        emergency: false,
     };
 
     CONFIG.save(deps.storage, &config)?;
 
     STATE.save(deps.storage, &state)?;
 
+    // DAO DAO instantiates its voting module itself
+    DAO.save(deps.storage, &info.sender)?;
+
+    cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     let res = Response::new()
         .add_attribute("action", "instantiate")
         .add_attribute("admin", admin.to_string())
-        .add_attribute( "staked_token_denom", config.staked_token_denom)
-        .add_attribute("reward_denom", config.reward_denom);
+        .add_attribute(
+            "staked_token_denom",
+            denom_to_string(&config.staked_token_denom),
+        )
+        .add_attribute(
+            "reward_denoms",
+            state
+                .rewards
+                .iter()
+                .map(|reward| denom_to_string(&reward.denom))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
     Ok(res)
 }
 
@@ -68,185 +120,739 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateReward {} => execute_update_reward(deps, env, info),
-        ExecuteMsg::BondStake {} => execute_bond(deps, env, info),
+        ExecuteMsg::UpdateReward {} => {
+            let rewards = native_funds(&info)?;
+            execute_update_reward(deps, env, rewards)
+        }
+        ExecuteMsg::BondStake { recipient } => {
+            let config = CONFIG.load(deps.storage)?;
+            let amount = must_pay_native(&info, &config.staked_token_denom)?;
+            let recipient = maybe_addr(deps.api, recipient)?.unwrap_or_else(|| info.sender.clone());
+            execute_bond(deps, env, info.sender, recipient, amount)
+        }
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
         ExecuteMsg::UpdateHolderReward { address } => {
             execute_update_holder_rewards(deps, env, info, address)
         }
-        ExecuteMsg::WithdrawStake { amount } => execute_withdraw(deps, env, info, amount),
-        ExecuteMsg::ReceiveReward {} => execute_receive_reward(deps, env, info),
-        ExecuteMsg::AdminWithdrawAll {} => execute_admin_withdraw_all(deps, env, info),
-        ExecuteMsg::UpdateAdmin { address } => execute_update_admin(deps, env, info, address),
+        ExecuteMsg::WithdrawStake { amount, owner } => {
+            execute_withdraw(deps, env, info, amount, owner)
+        }
+        ExecuteMsg::Claim {} => execute_claim(deps, env, info),
+        ExecuteMsg::ReceiveReward {
+            recipient,
+            call,
+            owner,
+        } => execute_receive_reward(deps, env, info, recipient, call, owner),
+        ExecuteMsg::SetRewardRecipient { address } => {
+            execute_set_reward_recipient(deps, env, info, address)
+        }
+        ExecuteMsg::AdminWithdrawSurplus {} => execute_admin_withdraw_surplus(deps, env, info),
+        ExecuteMsg::AdminEmergencyWithdraw { assets } => {
+            execute_admin_emergency_withdraw(deps, env, info, assets)
+        }
+        ExecuteMsg::EmergencyExit {} => execute_emergency_exit(deps, env, info),
+        ExecuteMsg::ProposeAdmin { address, expiry } => {
+            execute_propose_admin(deps, env, info, address, expiry)
+        }
+        ExecuteMsg::AcceptAdmin {} => execute_accept_admin(deps, env, info),
+        ExecuteMsg::CancelAdminProposal {} => execute_cancel_admin_proposal(deps, env, info),
+        ExecuteMsg::RenounceAdmin {} => execute_renounce_admin(deps, env, info),
+        ExecuteMsg::SyncRewards {} => execute_sync_rewards(deps, env),
+        ExecuteMsg::Pause { operations } => execute_pause(deps, env, info, operations),
+        ExecuteMsg::Unpause { operations } => execute_unpause(deps, env, info, operations),
+        ExecuteMsg::UpdatePauser { address } => execute_update_pauser(deps, env, info, address),
+        ExecuteMsg::AddRewardDenom { denom } => execute_add_reward_denom(deps, env, info, denom),
+        ExecuteMsg::RemoveRewardDenom { denom } => {
+            execute_remove_reward_denom(deps, env, info, denom)
+        }
+        ExecuteMsg::RedistributeDust {} => execute_redistribute_dust(deps, env),
+        ExecuteMsg::Compound { owner } => execute_compound(deps, env, info, owner),
+        ExecuteMsg::SetAutoCompound { enabled } => {
+            execute_set_auto_compound(deps, env, info, enabled)
+        }
+        ExecuteMsg::TransferStake { recipient, amount } => {
+            execute_transfer_stake(deps, env, info, recipient, amount)
+        }
+        ExecuteMsg::SetTransfersEnabled { enabled } => {
+            execute_set_transfers_enabled(deps, env, info, enabled)
+        }
+        ExecuteMsg::ApproveOperator { operator, expires } => {
+            execute_approve_operator(deps, env, info, operator, expires)
+        }
+        ExecuteMsg::RevokeOperator { operator } => {
+            execute_revoke_operator(deps, env, info, operator)
+        }
     }
 }
 
-pub fn execute_update_reward(
+pub fn execute_receive(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    let mut state = STATE.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+
+    // info.sender is the cw20 contract that forwarded the tokens
+    let token = Denom::Cw20(info.sender);
+    match msg {
+        ReceiveMsg::BondStake { recipient } => {
+            if token != config.staked_token_denom {
+                return Err(ContractError::InvalidToken {});
+            }
+            if wrapper.amount.is_zero() {
+                return Err(ContractError::AmountRequired {});
+            }
+            let recipient = maybe_addr(deps.api, recipient)?.unwrap_or_else(|| sender.clone());
+            execute_bond(deps, env, sender, recipient, wrapper.amount)
+        }
+        ReceiveMsg::UpdateRewardIndex {} => {
+            if wrapper.amount.is_zero() {
+                return Err(ContractError::AmountRequired {});
+            }
+            let rewards = vec![Asset {
+                denom: token,
+                amount: wrapper.amount,
+            }];
+            execute_update_reward(deps, env, rewards)
+        }
+    }
+}
 
+pub fn execute_update_reward(
+    deps: DepsMut,
+    env: Env,
+    rewards: Vec<Asset>,
+) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::UpdateReward) {
+        return Err(ContractError::Paused {});
+    }
 
-    // Check funds
-    let amount = must_pay(&info, &config.reward_denom)?;
-    /*  Zero staking check. This can be removed because this contract will be called by Stream Swap conract.
-     So this error will be thrown to Stream creator. But if that happens we can quickly change the fee_collector to multisig
-    while fixing the issue. */
-    if state.total_staked.is_zero() {
-        return Err(ContractError::NoBond {});
+    // check that only active reward denoms are sent
+    if let Some(asset) = rewards.iter().find(|asset| {
+        !state
+            .rewards
+            .iter()
+            .any(|reward| reward.active && reward.denom == asset.denom)
+    }) {
+        return Err(ContractError::NotRewardDenom(denom_to_string(&asset.denom)));
     }
 
-    // update index
-    state.global_index = state
-        .global_index
-        .checked_add(Decimal256::from_ratio(amount, state.total_staked))?;
+    // distribute what is streamed so far before the rates change
+    let now = env.block.time.seconds();
+    update_global_indexes(&mut state, now)?;
 
-    state.total_rewards = state.total_rewards.add(amount);
+    // deposits are parked as undistributed rewards until stake is bonded
+    for asset in rewards.iter() {
+        let reward = state
+            .reward_mut(&asset.denom)
+            .ok_or_else(|| ContractError::NotRewardDenom(denom_to_string(&asset.denom)))?;
 
-    STATE.save(deps.storage, &state)?;
+        reward.undistributed_rewards = reward.undistributed_rewards.checked_add(asset.amount)?;
+        reward.total_rewards = reward.total_rewards.checked_add(asset.amount)?;
+    }
+    distribute_undistributed_rewards(&mut state, config.reward_duration, now)?;
+
+    save_state(deps.storage, env.block.height, &state)?;
 
     let res = Response::new()
         .add_attribute("action", "update_reward")
-        .add_attribute("reward", amount.to_string());
+        .add_attribute("reward", assets_to_string(&rewards));
     Ok(res)
 }
 
+pub fn execute_sync_rewards(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::UpdateReward) {
+        return Err(ContractError::Paused {});
+    }
+
+    let now = env.block.time.seconds();
+    update_global_indexes(&mut state, now)?;
+
+    // surplus is the balance that is not owed to holders yet
+    let obligations = obligations(&config, &state)?;
+    let mut synced: Vec<Asset> = vec![];
+    for reward in state.rewards.iter_mut().filter(|reward| reward.active) {
+        let balance = query_token_balance(deps.as_ref(), &reward.denom, &env.contract.address)?;
+        let owed = obligation_of(&obligations, &reward.denom);
+        let surplus = balance.saturating_sub(owed);
+        if surplus.is_zero() {
+            continue;
+        }
+        reward.undistributed_rewards = reward.undistributed_rewards.checked_add(surplus)?;
+        reward.total_rewards = reward.total_rewards.checked_add(surplus)?;
+        synced.push(Asset {
+            denom: reward.denom.clone(),
+            amount: surplus,
+        });
+    }
+    if synced.is_empty() {
+        return Err(ContractError::NoRewards {});
+    }
+    distribute_undistributed_rewards(&mut state, config.reward_duration, now)?;
+
+    save_state(deps.storage, env.block.height, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "sync_rewards")
+        .add_attribute("reward", assets_to_string(&synced)))
+}
+
+pub fn execute_redistribute_dust(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::UpdateReward) {
+        return Err(ContractError::Paused {});
+    }
+    if state.total_staked.is_zero() {
+        return Err(ContractError::NoBond {});
+    }
+
+    update_global_indexes(&mut state, env.block.time.seconds())?;
+
+    // dust is already counted in the total rewards, it only moves back into the index
+    let total_staked = state.total_staked;
+    let mut redistributed: Vec<String> = vec![];
+    for reward in state.rewards.iter_mut() {
+        if reward.dust.is_zero() {
+            continue;
+        }
+        let dust = reward.dust;
+        reward.dust = Uint256::zero();
+        let previous_index = reward.global_index;
+        increase_global_index(reward, dust, total_staked)?;
+        // report the scaled amount the index increase pays out, the carried over remainder
+        // included, whole tokens would hide dust below one token
+        let added = reward
+            .global_index
+            .checked_sub(previous_index)?
+            .checked_mul(Uint256::from(total_staked))?;
+        redistributed.push(format!("{}{}", added, denom_to_string(&reward.denom)));
+    }
+    if redistributed.is_empty() {
+        return Err(ContractError::NoDust {});
+    }
+
+    save_state(deps.storage, env.block.height, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "redistribute_dust")
+        .add_attribute("scaled_dust", redistributed.join(",")))
+}
+
 pub fn execute_update_holder_rewards(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     address: Option<String>,
 ) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
     let mut state = STATE.load(deps.storage)?;
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::UpdateHolder) {
+        return Err(ContractError::Paused {});
+    }
 
     //validate address
     let addr = maybe_addr(deps.api, address)?.unwrap_or(info.sender);
     let mut holder = HOLDERS.load(deps.storage, &addr)?;
-    update_holder_rewards(deps.branch(), &mut state, env, &mut holder)?;
-    HOLDERS.save(deps.storage, &Addr::unchecked(addr), &holder)?;
-    STATE.save(deps.storage, &state)?;
+    update_holder_rewards(deps.branch(), &mut state, &env, &mut holder)?;
+    save_holder(deps.storage, env.block.height, &addr, &holder)?;
+    save_state(deps.storage, env.block.height, &state)?;
+
+    let pending_rewards: Vec<Asset> = holder
+        .rewards
+        .iter()
+        .map(|reward| Asset {
+            denom: reward.denom.clone(),
+            amount: reward.pending_rewards,
+        })
+        .collect();
 
     let res = Response::new()
         .add_attribute("action", "update_reward_index")
-        .add_attribute("pending_rewards", holder.pending_rewards)
-        .add_attribute("new_index", state.global_index.to_string())
-        .add_attribute("holders index", holder.index.to_string());
+        .add_attribute("pending_rewards", assets_to_string(&pending_rewards));
     Ok(res)
 }
 
+// advance the global indexes by the rewards streamed since the last update
+pub fn update_global_indexes(state: &mut State, now: u64) -> StdResult<()> {
+    let total_staked = state.total_staked;
+    for reward in state.rewards.iter_mut() {
+        let applicable_time = now.min(reward.period_finish);
+        if applicable_time <= reward.last_update_time {
+            continue;
+        }
+        let elapsed = applicable_time - reward.last_update_time;
+        let streamed = reward.reward_rate.checked_mul(Uint256::from(elapsed))?;
+        if total_staked.is_zero() {
+            // rewards streamed while nothing is staked are kept for the next stakers
+            let (streamed_rewards, remainder) = floor_with_remainder(streamed)?;
+            reward.undistributed_rewards =
+                reward.undistributed_rewards.checked_add(streamed_rewards)?;
+            reward.index_remainder = reward.index_remainder.checked_add(remainder)?;
+        } else {
+            increase_global_index(reward, streamed, total_staked)?;
+        }
+        reward.last_update_time = applicable_time;
+    }
+    Ok(())
+}
+
+// add scaled rewards to the global index, what doesn't divide evenly is kept for the next increase
+pub fn increase_global_index(
+    reward: &mut RewardState,
+    scaled_amount: Uint256,
+    total_staked: Uint128,
+) -> StdResult<()> {
+    let (delta, remainder) = index_delta(scaled_amount, reward.index_remainder, total_staked)?;
+    reward.global_index = reward.global_index.checked_add(delta)?;
+    reward.index_remainder = remainder;
+    Ok(())
+}
+
+// distribute the undistributed rewards instantly or stream them over the reward duration,
+// they stay parked while nothing is staked
+pub fn distribute_undistributed_rewards(
+    state: &mut State,
+    reward_duration: Option<u64>,
+    now: u64,
+) -> Result<(), ContractError> {
+    let total_staked = state.total_staked;
+    if total_staked.is_zero() {
+        return Ok(());
+    }
+    for reward in state.rewards.iter_mut() {
+        let amount = reward.undistributed_rewards;
+        if amount.is_zero() {
+            continue;
+        }
+        let scaled_amount = scale(amount)?;
+        match reward_duration {
+            Some(duration) => {
+                // stream the amount together with the not yet streamed rewards, a deposit
+                // smaller than what is left only tops up the current stream so small deposits
+                // can't keep delaying the end of the period
+                let (remaining, period) = if now < reward.period_finish {
+                    let left = reward.period_finish - now;
+                    let remaining = reward.reward_rate.checked_mul(Uint256::from(left))?;
+                    if scaled_amount < remaining {
+                        (remaining, left)
+                    } else {
+                        (remaining, duration)
+                    }
+                } else {
+                    (Uint256::zero(), duration)
+                };
+                let streamed = scaled_amount.checked_add(remaining)?;
+                let period_256 = Uint256::from(period);
+                reward.reward_rate = streamed.checked_div(period_256).map_err(StdError::from)?;
+                // what doesn't divide evenly by the period is distributed with the next increase
+                reward.index_remainder = reward
+                    .index_remainder
+                    .checked_add(streamed.checked_rem(period_256).map_err(StdError::from)?)?;
+                reward.period_finish = now
+                    .checked_add(period)
+                    .ok_or(ContractError::InvalidRewardDuration {})?;
+                reward.last_update_time = now;
+            }
+            None => increase_global_index(reward, scaled_amount, total_staked)?,
+        }
+        reward.undistributed_rewards = Uint128::zero();
+    }
+    Ok(())
+}
+
 pub fn update_holder_rewards(
-    mut deps: DepsMut,
+    deps: DepsMut,
     state: &mut State,
-    env: Env,
+    env: &Env,
     holder: &mut Holder,
-) -> Result<Uint128, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
+) -> Result<(), ContractError> {
+    update_global_indexes(state, env.block.time.seconds())?;
+    settle_holder_rewards(state, holder)?;
+    if holder.auto_compound {
+        // the rewards stay pending while compounding is paused
+        let config = CONFIG.load(deps.storage)?;
+        if !config.is_compounding_paused(state) {
+            compound_rewards(&config, state, holder)?;
+        }
+    }
+
+    Ok(())
+}
+
+// move the rewards accrued since the holder's last index to pending rewards
+pub fn settle_holder_rewards(state: &State, holder: &mut Holder) -> StdResult<()> {
+    let balance = holder.balance;
+    for reward in state.rewards.iter() {
+        let holder_reward = holder.reward_mut(&reward.denom);
+
+        //scaled_rewards = holder.balance * (global_index - holder.index) + holder.dec_rewards;
+        let scaled_rewards = accrual(
+            balance,
+            reward.global_index,
+            holder_reward.index,
+            holder_reward.dec_rewards,
+        )?;
 
-    let rewards_uint128;
+        //floor(scaled_rewards), the remainder is carried over to the next update
+        let (rewards, dec_rewards) = floor_with_remainder(scaled_rewards)?;
+        holder_reward.dec_rewards = dec_rewards;
 
-    //index_diff = global_index - holder.index;
-    let index_diff: Decimal256 = state.global_index - holder.index;
+        holder_reward.pending_rewards = holder_reward.pending_rewards.checked_add(rewards)?;
 
-    //reward_amount = holder.balance * index_diff + holder.pending_rewards;
-    let reward_amount = Decimal256::from_ratio(holder.balance, Uint256::one())
-        .checked_mul(index_diff)?
-        .checked_add(holder.dec_rewards)?;
-    let decimals = get_decimals(reward_amount)?;
+        holder_reward.index = reward.global_index;
+    }
 
-    //floor(reward_amount)
-    rewards_uint128 = (reward_amount * Uint256::one())
-        .try_into()
-        .unwrap_or(Uint128::zero());
+    Ok(())
+}
 
-    holder.dec_rewards = decimals;
+// bond the pending rewards of the staked token and return the amount
+pub fn compound_rewards(
+    config: &Config,
+    state: &mut State,
+    holder: &mut Holder,
+) -> StdResult<Uint128> {
+    let staked_token_denom = &config.staked_token_denom;
+    let reward = match state.reward_mut(staked_token_denom) {
+        Some(reward) => reward,
+        None => return Ok(Uint128::zero()),
+    };
+    let holder_reward = holder.reward_mut(staked_token_denom);
+    let amount = holder_reward.pending_rewards;
+    if amount.is_zero() {
+        return Ok(amount);
+    }
 
-    holder.pending_rewards += rewards_uint128;
+    // the rewards leave the pool and become stake
+    reward.rewards_claimed = reward.rewards_claimed.checked_add(amount)?;
+    holder_reward.pending_rewards = Uint128::zero();
+    holder.balance = holder.balance.checked_add(amount)?;
+    state.total_staked = state.total_staked.checked_add(amount)?;
+    Ok(amount)
+}
 
-    holder.index = state.global_index;
+// keep the fractional rewards of a removed holder as dust
+pub fn collect_dust(state: &mut State, holder: &Holder) -> StdResult<()> {
+    for holder_reward in holder.rewards.iter() {
+        if let Some(reward) = state.reward_mut(&holder_reward.denom) {
+            reward.dust = reward.dust.checked_add(holder_reward.dec_rewards)?;
+        }
+    }
+    Ok(())
+}
 
-    Ok(rewards_uint128)
+// move all pending rewards of the holder to claimed and return them
+pub fn claim_rewards(state: &mut State, holder: &mut Holder) -> Result<Vec<Asset>, ContractError> {
+    let mut claimed: Vec<Asset> = vec![];
+    for holder_reward in holder.rewards.iter_mut() {
+        if holder_reward.pending_rewards.is_zero() {
+            continue;
+        }
+        if let Some(reward) = state.reward_mut(&holder_reward.denom) {
+            reward.rewards_claimed = reward
+                .rewards_claimed
+                .checked_add(holder_reward.pending_rewards)?;
+        }
+        claimed.push(Asset {
+            denom: holder_reward.denom.clone(),
+            amount: holder_reward.pending_rewards,
+        });
+        holder_reward.pending_rewards = Uint128::zero();
+    }
+    Ok(claimed)
 }
 
 pub fn execute_receive_reward(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    recipient: Option<String>,
+    call: Option<ContractCall>,
+    owner: Option<String>,
 ) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
     let mut state = STATE.load(deps.storage)?;
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::Claim) {
+        return Err(ContractError::Paused {});
+    }
+    if recipient.is_some() && call.is_some() {
+        return Err(ContractError::RecipientAndCall {});
+    }
 
-    let config = CONFIG.load(deps.storage)?;
+    let owner = holder_or_operator(deps.as_ref(), &env, &info.sender, owner)?;
+    // operators can only claim to the holder's reward recipient
+    if owner != info.sender && (recipient.is_some() || call.is_some()) {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    let mut holder = HOLDERS.load(deps.storage, &info.sender)?;
+    let recipient = match (maybe_addr(deps.api, recipient)?, &call) {
+        (Some(recipient), _) => recipient,
+        (None, Some(call)) => {
+            let target = deps.api.addr_validate(&call.contract_addr)?;
+            // the call is sent by the pool, it must never reach the tokens it holds
+            let target_denom = Denom::Cw20(target.clone());
+            if target == env.contract.address
+                || config.staked_token_denom == target_denom
+                || state
+                    .rewards
+                    .iter()
+                    .any(|reward| reward.denom == target_denom)
+            {
+                return Err(ContractError::InvalidCallTarget {});
+            }
+            target
+        }
+        (None, None) => reward_recipient(deps.as_ref(), &owner)?,
+    };
+    let mut holder = HOLDERS.load(deps.storage, &owner)?;
 
-    update_holder_rewards(deps.branch(), &mut state, env, &mut holder)?;
+    update_holder_rewards(deps.branch(), &mut state, &env, &mut holder)?;
 
-    if holder.pending_rewards.is_zero() {
+    let rewards = claim_rewards(&mut state, &mut holder)?;
+    if rewards.is_empty() {
         return Err(ContractError::NoRewards {});
     }
-    //send rewards to the holder
-    let send_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: info.sender.to_string(),
-        amount: vec![Coin {
-            denom: config.reward_denom.to_string(),
-            amount: holder.pending_rewards,
-        }],
-    });
-    state.rewards_claimed += holder.pending_rewards;
-
-    holder.pending_rewards = Uint128::zero();
 
-    HOLDERS.save(deps.storage, &info.sender, &holder)?;
-    STATE.save(deps.storage, &state)?;
+    save_holder(deps.storage, env.block.height, &owner, &holder)?;
+    save_state(deps.storage, env.block.height, &state)?;
 
+    //send rewards to the recipient or along with the call
+    let msgs = match call {
+        Some(call) => call_with_assets(&recipient, call.msg, &rewards)?,
+        None => send_assets(&recipient, &rewards)?,
+    };
     Ok(Response::new()
-        .add_message(send_msg)
+        .add_messages(msgs)
         .add_attribute("action", "receive_reward")
-        .add_attribute("rewards", holder.pending_rewards)
-        .add_attribute("holder", info.sender)
-        .add_attribute("holder_balance", holder.balance))
+        .add_attribute("rewards", assets_to_string(&rewards))
+        .add_attribute("holder", owner)
+        .add_attribute("holder_balance", holder.balance)
+        .add_attribute("recipient", recipient))
 }
 
-pub fn execute_bond(
+pub fn execute_approve_operator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+
+    OPERATORS.save(deps.storage, (&info.sender, &operator), &expires)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "approve_operator")
+        .add_attribute("owner", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn execute_revoke_operator(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operator: String,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+
+    OPERATORS.remove(deps.storage, (&info.sender, &operator));
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_operator")
+        .add_attribute("owner", info.sender)
+        .add_attribute("operator", operator))
+}
+
+// the owner if the sender is the owner or one of its unexpired operators, the sender if not set
+pub fn holder_or_operator(
+    deps: Deps,
+    env: &Env,
+    sender: &Addr,
+    owner: Option<String>,
+) -> Result<Addr, ContractError> {
+    let owner = match maybe_addr(deps.api, owner)? {
+        Some(owner) => owner,
+        None => return Ok(sender.clone()),
+    };
+    if &owner == sender {
+        return Ok(owner);
+    }
+    match OPERATORS.may_load(deps.storage, (&owner, sender))? {
+        Some(expires) if !expires.is_expired(&env.block) => Ok(owner),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+pub fn execute_set_reward_recipient(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: Option<String>,
+) -> Result<Response, ContractError> {
+    // the setting is kept when the holder withdraws everything
+    let recipient = maybe_addr(deps.api, address)?;
+    match &recipient {
+        Some(recipient) => REWARD_RECIPIENTS.save(deps.storage, &info.sender, recipient)?,
+        None => REWARD_RECIPIENTS.remove(deps.storage, &info.sender),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_reward_recipient")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute(
+            "recipient",
+            recipient
+                .map(|recipient| recipient.to_string())
+                .unwrap_or_default(),
+        ))
+}
+
+// address the rewards of the holder are sent to, the holder itself if not set
+pub fn reward_recipient(deps: Deps, holder: &Addr) -> StdResult<Addr> {
+    Ok(REWARD_RECIPIENTS
+        .may_load(deps.storage, holder)?
+        .unwrap_or_else(|| holder.clone()))
+}
+
+pub fn execute_compound(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    owner: Option<String>,
 ) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
     let mut state = STATE.load(deps.storage)?;
+    if !config.allow_compounding {
+        return Err(ContractError::CompoundingDisabled {});
+    }
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_compounding_paused(&state) {
+        return Err(ContractError::Paused {});
+    }
+
+    let owner = holder_or_operator(deps.as_ref(), &env, &info.sender, owner)?;
+    let mut holder = HOLDERS.load(deps.storage, &owner)?;
+    update_holder_rewards(deps.branch(), &mut state, &env, &mut holder)?;
+
+    let amount = compound_rewards(&config, &mut state, &mut holder)?;
+    if amount.is_zero() {
+        return Err(ContractError::NoRewards {});
+    }
+
+    save_holder(deps.storage, env.block.height, &owner, &holder)?;
+    save_state(deps.storage, env.block.height, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "compound")
+        .add_attribute("holder_address", owner)
+        .add_attribute("amount", amount)
+        .add_attribute("holder_balance", holder.balance))
+}
+
+pub fn execute_set_auto_compound(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if !config.allow_compounding {
+        return Err(ContractError::CompoundingDisabled {});
+    }
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::UpdateHolder) {
+        return Err(ContractError::Paused {});
+    }
+
+    // rewards accrued so far are settled with the previous setting
+    let mut holder = HOLDERS.load(deps.storage, &info.sender)?;
+    update_holder_rewards(deps.branch(), &mut state, &env, &mut holder)?;
+    holder.auto_compound = enabled;
+
+    save_holder(deps.storage, env.block.height, &info.sender, &holder)?;
+    save_state(deps.storage, env.block.height, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_auto_compound")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("enabled", enabled.to_string()))
+}
 
-    //check if denom sent is the same as the staked token else return error
-    let amount = must_pay(&info, &config.staked_token_denom)?;
-    let addr = info.sender;
- 
+// bond the stake paid by the funder for the holder at addr
+pub fn execute_bond(
+    mut deps: DepsMut,
+    env: Env,
+    funder: Addr,
+    addr: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::Bond) {
+        return Err(ContractError::Paused {});
+    }
+    let now = env.block.time.seconds();
 
     let holder = HOLDERS.may_load(deps.storage, &addr)?;
 
     match holder {
         None => {
-            let holder = Holder::new(
-                amount,
-                state.global_index,
-                Uint128::zero(),
-                Decimal256::zero(),
-            );
-            HOLDERS.save(deps.storage, &addr, &holder)?;
+            update_global_indexes(&mut state, now)?;
+            let holder = Holder::new(amount, &state);
+            save_holder(deps.storage, env.block.height, &addr, &holder)?;
         }
         Some(mut holder) => {
-            update_holder_rewards(deps.branch(), &mut state, env, &mut holder)?;
-            holder.balance += amount;
+            update_holder_rewards(deps.branch(), &mut state, &env, &mut holder)?;
+            holder.balance = holder.balance.checked_add(amount)?;
 
-            HOLDERS.save(deps.storage, &addr, &holder)?;
+            save_holder(deps.storage, env.block.height, &addr, &holder)?;
         }
     }
-    state.total_staked += amount;
-    STATE.save(deps.storage, &state)?;
+    state.total_staked = state.total_staked.checked_add(amount)?;
+
+    // rewards received while nothing was staked go to the first stakers
+    distribute_undistributed_rewards(&mut state, config.reward_duration, now)?;
+    save_state(deps.storage, env.block.height, &state)?;
 
     let res = Response::new()
         .add_attribute("action", "bond_stake")
         .add_attribute("holder_address", addr)
-        .add_attribute("amount", amount);
+        .add_attribute("amount", amount)
+        .add_attribute("funder", funder);
 
     Ok(res)
 }
@@ -256,108 +862,608 @@ pub fn execute_withdraw(
     env: Env,
     info: MessageInfo,
     amount: Option<Uint128>,
+    owner: Option<String>,
 ) -> Result<Response, ContractError> {
     let mut state = STATE.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
     if !info.funds.is_empty() {
         return Err(ContractError::DoNotSendFunds {});
     }
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::Withdraw) {
+        return Err(ContractError::Paused {});
+    }
 
-    let mut holder = HOLDERS.load(deps.storage, &info.sender)?;
-    let withdraw_amount = amount.unwrap_or(holder.balance);
+    // stake and rewards always go to the holder, never to the operator
+    let owner = holder_or_operator(deps.as_ref(), &env, &info.sender, owner)?;
+    let mut holder = HOLDERS.load(deps.storage, &owner)?;
 
+    // auto compounded rewards can be withdrawn right away
+    update_holder_rewards(deps.branch(), &mut state, &env, &mut holder)?;
+
+    let withdraw_amount = amount.unwrap_or(holder.balance);
     if holder.balance < withdraw_amount {
         return Err(ContractError::DecreaseAmountExceeds(holder.balance));
     }
 
-    update_holder_rewards(deps.branch(), &mut state, env.clone(), &mut holder)?;
+    //send rewards to the holder's reward recipient
+    let rewards = claim_rewards(&mut state, &mut holder)?;
+    let recipient = reward_recipient(deps.as_ref(), &owner)?;
+    let mut res = Response::new().add_messages(send_assets(&recipient, &rewards)?);
+
+    //send withdraw amount to the holder or lock it until the unbonding period is over
+    match config.unbonding_period {
+        Some(unbonding_period) => {
+            CLAIMS.create_claim(
+                deps.storage,
+                &owner,
+                withdraw_amount,
+                unbonding_period.after(&env.block),
+            )?;
+            state.total_unbonding = state.total_unbonding.checked_add(withdraw_amount)?;
+        }
+        None => {
+            res = res.add_message(send_tokens(
+                &owner,
+                &config.staked_token_denom,
+                withdraw_amount,
+            )?);
+        }
+    }
 
-    //send rewards and withdraw amount to the holder
-    let res: Response = Response::new()
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: info.sender.to_string(),
-            amount: vec![Coin {
-                denom: config.reward_denom.to_string(),
-                amount: holder.pending_rewards,
-            }],
-        }))
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: info.sender.to_string(),
-            amount: vec![Coin {
-                denom: config.staked_token_denom.to_string(),
-                amount: withdraw_amount,
-            }],
-        }))
+    let res = res
         .add_attribute("action", "withdraw_stake")
-        .add_attribute("holder_address", info.sender.clone())
+        .add_attribute("holder_address", owner.clone())
         .add_attribute("amount", withdraw_amount)
-        .add_attribute("rewards claimed", holder.pending_rewards);
+        .add_attribute("rewards claimed", assets_to_string(&rewards));
 
     holder.balance = (holder.balance.checked_sub(withdraw_amount))?;
     state.total_staked = (state.total_staked.checked_sub(withdraw_amount))?;
-    holder.pending_rewards = Uint128::zero();
     if holder.balance.is_zero() {
-        HOLDERS.remove(deps.storage, &info.sender);
+        collect_dust(&mut state, &holder)?;
+        remove_holder(deps.storage, env.block.height, &owner)?;
     } else {
-        HOLDERS.save(deps.storage, &info.sender, &holder)?;
+        save_holder(deps.storage, env.block.height, &owner, &holder)?;
     }
-    STATE.save(deps.storage, &state)?;
-    HOLDERS.save(deps.storage, &info.sender, &holder)?;
+    save_state(deps.storage, env.block.height, &state)?;
     Ok(res)
 }
-pub fn execute_update_admin(
+
+pub fn execute_transfer_stake(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    new_admin: String,
+    recipient: String,
+    amount: Uint128,
 ) -> Result<Response, ContractError> {
-    let mut config = CONFIG.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if !config.transfers_enabled {
+        return Err(ContractError::TransfersDisabled {});
+    }
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    if config.is_paused(Operation::Transfer) {
+        return Err(ContractError::Paused {});
+    }
 
-    if info.sender != config.admin {
-        return Err(ContractError::Unauthorized {});
+    let recipient = deps.api.addr_validate(&recipient)?;
+    if recipient == info.sender {
+        return Err(ContractError::SelfTransfer {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::AmountRequired {});
     }
 
-    config.admin = deps.api.addr_validate(&new_admin)?;
-    
-    CONFIG.save(deps.storage, &config)?;
+    // rewards accrued so far stay with the current balances
+    let mut holder = HOLDERS.load(deps.storage, &info.sender)?;
+    update_holder_rewards(deps.branch(), &mut state, &env, &mut holder)?;
+    if holder.balance < amount {
+        return Err(ContractError::DecreaseAmountExceeds(holder.balance));
+    }
+    let mut receiver = match HOLDERS.may_load(deps.storage, &recipient)? {
+        Some(mut receiver) => {
+            update_holder_rewards(deps.branch(), &mut state, &env, &mut receiver)?;
+            receiver
+        }
+        None => Holder::new(Uint128::zero(), &state),
+    };
+
+    holder.balance = holder.balance.checked_sub(amount)?;
+    receiver.balance = receiver.balance.checked_add(amount)?;
+
+    // the sender keeps its entry until its pending rewards are claimed
+    if holder.balance.is_zero()
+        && holder
+            .rewards
+            .iter()
+            .all(|reward| reward.pending_rewards.is_zero())
+    {
+        collect_dust(&mut state, &holder)?;
+        remove_holder(deps.storage, env.block.height, &info.sender)?;
+    } else {
+        save_holder(deps.storage, env.block.height, &info.sender, &holder)?;
+    }
+    save_holder(deps.storage, env.block.height, &recipient, &receiver)?;
+    save_state(deps.storage, env.block.height, &state)?;
 
     Ok(Response::new()
-        .add_attribute("action", "update_admin")
-        .add_attribute("admin", new_admin))
+        .add_attribute("action", "transfer_stake")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount))
 }
 
-
-
-pub fn execute_admin_withdraw_all(
-    mut deps: DepsMut,
-    env: Env,
+pub fn execute_set_transfers_enabled(
+    deps: DepsMut,
+    _env: Env,
     info: MessageInfo,
+    enabled: bool,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
+    let mut config = CONFIG.load(deps.storage)?;
 
-    if info.sender != config.admin {
+    if !config.is_admin(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
-    // all tokens
-    let all_tokens = deps
-        .branch()
-        .querier
-        .query_all_balances(&env.contract.address)?;
 
+    config.transfers_enabled = enabled;
+    CONFIG.save(deps.storage, &config)?;
 
-    let res = Response::new()
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: info.sender.to_string(),
-            amount: all_tokens,
-        }))
-        .add_attribute("action", "admin_withdraw_all")
-        .add_attribute("admin_address", info.sender.clone());
-    Ok(res)
+    Ok(Response::new()
+        .add_attribute("action", "set_transfers_enabled")
+        .add_attribute("enabled", enabled.to_string()))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
+pub fn execute_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+    // unbonded stake stays claimable while only reward claims are paused
+    if config.is_paused(Operation::Withdraw) {
+        return Err(ContractError::Paused {});
+    }
+
+    let release = CLAIMS.claim_tokens(deps.storage, &info.sender, &env.block, None)?;
+    if release.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+    state.total_unbonding = state.total_unbonding.checked_sub(release)?;
+    save_state(deps.storage, env.block.height, &state)?;
+
+    Ok(Response::new()
+        .add_message(send_tokens(
+            &info.sender,
+            &config.staked_token_denom,
+            release,
+        )?)
+        .add_attribute("action", "claim")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("amount", release))
+}
+
+pub fn execute_propose_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    expiry: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if expiry.is_some_and(|expiry| expiry.is_expired(&env.block)) {
+        return Err(ContractError::AdminProposalExpired {});
+    }
+
+    // a new proposal replaces the previous one
+    let pending_admin = PendingAdmin {
+        address: deps.api.addr_validate(&address)?,
+        expiry,
+    };
+    PENDING_ADMIN.save(deps.storage, &pending_admin)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_admin")
+        .add_attribute("pending_admin", pending_admin.address))
+}
+
+pub fn execute_accept_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    let pending_admin = PENDING_ADMIN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingAdmin {})?;
+    if info.sender != pending_admin.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    if pending_admin
+        .expiry
+        .is_some_and(|expiry| expiry.is_expired(&env.block))
+    {
+        return Err(ContractError::AdminProposalExpired {});
+    }
+
+    config.admin = Some(pending_admin.address);
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_ADMIN.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_admin")
+        .add_attribute("admin", info.sender))
+}
+
+pub fn execute_cancel_admin_proposal(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if PENDING_ADMIN.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoPendingAdmin {});
+    }
+
+    PENDING_ADMIN.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "cancel_admin_proposal"))
+}
+
+pub fn execute_renounce_admin(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.admin = None;
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_ADMIN.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "renounce_admin")
+        .add_attribute("admin", info.sender))
+}
+
+pub fn execute_pause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operations: Vec<Operation>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) && Some(&info.sender) != config.pauser.as_ref() {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for operation in operations.iter() {
+        if !config.is_paused(*operation) {
+            config.paused.push(*operation);
+        }
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "pause")
+        .add_attribute("operations", operations_to_string(&operations)))
+}
+
+pub fn execute_unpause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operations: Vec<Operation>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) && Some(&info.sender) != config.pauser.as_ref() {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config
+        .paused
+        .retain(|operation| !operations.contains(operation));
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "unpause")
+        .add_attribute("operations", operations_to_string(&operations)))
+}
+
+pub fn execute_update_pauser(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.pauser = maybe_addr(deps.api, address)?;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_pauser")
+        .add_attribute(
+            "pauser",
+            config
+                .pauser
+                .map(|pauser| pauser.to_string())
+                .unwrap_or_default(),
+        ))
+}
+
+pub fn execute_add_reward_denom(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    denom: Denom,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let denom = validate_denom(deps.as_ref(), denom)?;
+    if denom == config.staked_token_denom && !config.allow_compounding {
+        return Err(ContractError::SameDenom {});
+    }
+
+    // a removed denom is reactivated so holders keep accruing from its last index
+    match state.reward_mut(&denom) {
+        Some(reward) if reward.active => return Err(ContractError::DuplicateDenom {}),
+        Some(reward) => reward.active = true,
+        None => state.rewards.push(RewardState::new(denom.clone())),
+    }
+
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_reward_denom")
+        .add_attribute("denom", denom_to_string(&denom)))
+}
+
+pub fn execute_remove_reward_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: Denom,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let now = env.block.time.seconds();
+    update_global_indexes(&mut state, now)?;
+    let reward = state
+        .reward_mut(&denom)
+        .filter(|reward| reward.active)
+        .ok_or_else(|| ContractError::NotRewardDenom(denom_to_string(&denom)))?;
+
+    // every deposit must be distributed to the holders before the denom is removed, holders
+    // keep claiming what they are owed afterwards
+    if !reward.undistributed_rewards.is_zero() || reward.period_finish > now {
+        return Err(ContractError::UndistributedRewards {});
+    }
+    reward.active = false;
+
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_reward_denom")
+        .add_attribute("denom", denom_to_string(&denom)))
+}
+
+pub fn execute_admin_withdraw_surplus(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if state.emergency {
+        return Err(ContractError::EmergencyMode {});
+    }
+
+    // only the balance above the stake and the rewards owed to holders can be withdrawn
+    let obligations = obligations(&config, &state)?;
+    let mut surplus: Vec<Asset> = vec![];
+    for obligation in obligations.iter() {
+        let balance = query_token_balance(deps.as_ref(), &obligation.denom, &env.contract.address)?;
+        if balance > obligation.amount {
+            surplus.push(Asset {
+                denom: obligation.denom.clone(),
+                amount: balance - obligation.amount,
+            });
+        }
+    }
+    // native tokens the contract does not track can be withdrawn entirely
+    for coin in deps.querier.query_all_balances(&env.contract.address)? {
+        let denom = Denom::Native(coin.denom);
+        if !obligations
+            .iter()
+            .any(|obligation| obligation.denom == denom)
+        {
+            surplus.push(Asset {
+                denom,
+                amount: coin.amount,
+            });
+        }
+    }
+    if surplus.iter().all(|asset| asset.amount.is_zero()) {
+        return Err(ContractError::NoSurplus {});
+    }
+
+    Ok(Response::new()
+        .add_messages(send_assets(&info.sender, &surplus)?)
+        .add_attribute("action", "admin_withdraw_surplus")
+        .add_attribute("admin_address", info.sender)
+        .add_attribute("surplus", assets_to_string(&surplus)))
+}
+
+pub fn execute_admin_emergency_withdraw(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    assets: Vec<Asset>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // bonding and distribution are frozen from now on, holders exit pro rata
+    state.emergency = true;
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_messages(send_assets(&info.sender, &assets)?)
+        .add_attribute("action", "admin_emergency_withdraw")
+        .add_attribute("admin_address", info.sender)
+        .add_attribute("assets", assets_to_string(&assets)))
+}
+
+pub fn execute_emergency_exit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if !state.emergency {
+        return Err(ContractError::NotEmergencyMode {});
+    }
+
+    // settle the holder so the rewards accrued until now are owed to it
+    update_global_indexes(&mut state, env.block.time.seconds())?;
+    let holder = match HOLDERS.may_load(deps.storage, &info.sender)? {
+        Some(mut holder) => {
+            settle_holder_rewards(&state, &mut holder)?;
+            Some(holder)
+        }
+        None => None,
+    };
+
+    // bonded and unbonding stake both count for the share of the holder
+    let balance = holder
+        .as_ref()
+        .map(|holder| holder.balance)
+        .unwrap_or_default();
+    let unbonding: Uint128 = CLAIMS
+        .query_claims(deps.as_ref(), &info.sender)?
+        .claims
+        .iter()
+        .map(|claim| claim.amount)
+        .sum();
+    let share = balance + unbonding;
+    let pending_rewards = |denom: &Denom| {
+        holder
+            .as_ref()
+            .and_then(|holder| holder.rewards.iter().find(|reward| &reward.denom == denom))
+            .map(|reward| reward.pending_rewards)
+            .unwrap_or_default()
+    };
+    let has_rewards = state
+        .rewards
+        .iter()
+        .any(|reward| !pending_rewards(&reward.denom).is_zero());
+    if share.is_zero() && !has_rewards {
+        return Err(ContractError::NoBond {});
+    }
+    let total_shares = state.total_staked + state.total_unbonding;
+
+    // the staked token is paid once even if it is also a reward denom
+    let mut denoms = vec![config.staked_token_denom.clone()];
+    for reward in state.rewards.iter() {
+        if !denoms.contains(&reward.denom) {
+            denoms.push(reward.denom.clone());
+        }
+    }
+    let mut assets: Vec<Asset> = vec![];
+    for denom in denoms {
+        let mut remaining = query_token_balance(deps.as_ref(), &denom, &env.contract.address)?;
+        let mut amount = Uint128::zero();
+        // stake is paid by stake share, it comes first when the staked token is also a reward
+        if denom == config.staked_token_denom {
+            let principal = remaining.min(total_shares);
+            if !share.is_zero() {
+                amount += pro_rata(principal, share, total_shares)?;
+            }
+            remaining -= principal;
+        }
+        // rewards are paid by the share of the rewards still owed to holders
+        let pending = pending_rewards(&denom);
+        if let Some(reward) = state.reward_mut(&denom) {
+            if !pending.is_zero() {
+                let owed = reward.total_rewards.checked_sub(reward.rewards_claimed)?;
+                amount += pro_rata(remaining, pending, owed)?;
+                reward.rewards_claimed = reward.rewards_claimed.checked_add(pending)?;
+            }
+        }
+        assets.push(Asset { denom, amount });
+    }
+
+    // release all claims regardless of their expiration
+    let end_of_time = BlockInfo {
+        height: u64::MAX,
+        time: Timestamp::from_nanos(u64::MAX),
+        chain_id: env.block.chain_id,
+    };
+    CLAIMS.claim_tokens(deps.storage, &info.sender, &end_of_time, None)?;
+    remove_holder(deps.storage, env.block.height, &info.sender)?;
+    state.total_staked = state.total_staked.checked_sub(balance)?;
+    state.total_unbonding = state.total_unbonding.checked_sub(unbonding)?;
+    save_state(deps.storage, env.block.height, &state)?;
+
+    Ok(Response::new()
+        .add_messages(send_assets(&info.sender, &assets)?)
+        .add_attribute("action", "emergency_exit")
+        .add_attribute("holder_address", info.sender)
+        .add_attribute("assets", assets_to_string(&assets)))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
         QueryMsg::State {} => to_binary(&query_state(deps, env, msg)?),
         QueryMsg::AccruedRewards { address } => {
             to_binary(&query_accrued_rewards(env, deps, address)?)
@@ -367,6 +1473,25 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Holders { start_after, limit } => {
             to_binary(&query_holders(deps, env, start_after, limit)?)
         }
+        QueryMsg::Claims { address } => to_binary(&query_claims(deps, address)?),
+        QueryMsg::RewardDenoms {} => to_binary(&query_reward_denoms(deps)?),
+        QueryMsg::Solvency {} => to_binary(&query_solvency(deps, env)?),
+        QueryMsg::RewardRecipient { address } => to_binary(&query_reward_recipient(deps, address)?),
+        QueryMsg::Operators { owner } => to_binary(&query_operators(deps, env, owner)?),
+        QueryMsg::StakedBalanceAtHeight { address, height } => {
+            to_binary(&query_staked_balance_at_height(deps, env, address, height)?)
+        }
+        QueryMsg::TotalStakedAtHeight { height } => {
+            to_binary(&query_total_staked_at_height(deps, env, height)?)
+        }
+        QueryMsg::VotingPowerAtHeight { address, height } => {
+            to_binary(&query_voting_power_at_height(deps, env, address, height)?)
+        }
+        QueryMsg::TotalPowerAtHeight { height } => {
+            to_binary(&query_total_power_at_height(deps, env, height)?)
+        }
+        QueryMsg::Info {} => to_binary(&query_info(deps)?),
+        QueryMsg::Dao {} => to_binary(&DAO.load(deps.storage)?),
     }
 }
 
@@ -375,93 +1500,546 @@ pub fn query_state(deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<StateResp
 
     Ok(StateResponse {
         total_staked: state.total_staked,
-        global_index: state.global_index,
-        total_rewards: state.total_rewards,
-        rewards_claimed: state.rewards_claimed,
+        total_unbonding: state.total_unbonding,
+        rewards: state.rewards,
+        emergency: state.emergency,
     })
 }
 
 //query config
 pub fn query_config(deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
+    let pending_admin = PENDING_ADMIN.may_load(deps.storage)?;
 
     Ok(ConfigResponse {
         staked_token_denom: config.staked_token_denom,
-        reward_denom: config.reward_denom,
-        admin: config.admin.into_string(),
+        admin: config.admin.map(|admin| admin.into_string()),
+        pending_admin: pending_admin
+            .as_ref()
+            .map(|pending_admin| pending_admin.address.to_string()),
+        pending_admin_expiry: pending_admin.and_then(|pending_admin| pending_admin.expiry),
+        unbonding_period: config.unbonding_period,
+        reward_duration: config.reward_duration,
+        pauser: config.pauser.map(|pauser| pauser.into_string()),
+        paused: config.paused,
+        allow_compounding: config.allow_compounding,
+        transfers_enabled: config.transfers_enabled,
     })
 }
 
 pub fn query_accrued_rewards(
-    _env: Env,
+    env: Env,
     deps: Deps,
     address: String,
 ) -> StdResult<AccruedRewardsResponse> {
-    let addr = deps.api.addr_validate(&address.as_str())?;
+    let addr = deps.api.addr_validate(address.as_str())?;
     let holder = HOLDERS.load(deps.storage, &addr)?;
+    let config = CONFIG.load(deps.storage)?;
+    let state = current_state(deps, &env)?;
+
+    let updated = simulate_holder(&config, &state, &holder)?;
 
     Ok(AccruedRewardsResponse {
-        rewards: holder.pending_rewards,
+        rewards: updated
+            .rewards
+            .into_iter()
+            .map(|reward| AccruedReward {
+                settled: holder
+                    .rewards
+                    .iter()
+                    .find(|settled| settled.denom == reward.denom)
+                    .map(|settled| settled.pending_rewards)
+                    .unwrap_or_default(),
+                denom: reward.denom,
+                claimable: reward.pending_rewards,
+                dec_rewards: reward.dec_rewards,
+            })
+            .collect(),
     })
 }
 
-pub fn query_holder(_env: Env, deps: Deps, address: String) -> StdResult<HolderResponse> {
+pub fn query_holder(env: Env, deps: Deps, address: String) -> StdResult<HolderResponse> {
     let holder: Holder = HOLDERS.load(deps.storage, &deps.api.addr_validate(address.as_str())?)?;
+    let config = CONFIG.load(deps.storage)?;
+    let state = current_state(deps, &env)?;
+    holder_response(&config, &state, address, holder)
+}
+
+// state with the global indexes advanced to the current block, without saving it
+pub fn current_state(deps: Deps, env: &Env) -> StdResult<State> {
+    let mut state = STATE.load(deps.storage)?;
+    update_global_indexes(&mut state, env.block.time.seconds())?;
+    Ok(state)
+}
+
+// holder as if it was updated at the current block, without saving it
+pub fn simulate_holder(config: &Config, state: &State, holder: &Holder) -> StdResult<Holder> {
+    let mut updated = holder.clone();
+    settle_holder_rewards(state, &mut updated)?;
+    if updated.auto_compound && !config.is_compounding_paused(state) {
+        compound_rewards(config, &mut state.clone(), &mut updated)?;
+    }
+    Ok(updated)
+}
+
+fn holder_response(
+    config: &Config,
+    state: &State,
+    address: String,
+    holder: Holder,
+) -> StdResult<HolderResponse> {
+    let settled_rewards = holder
+        .rewards
+        .iter()
+        .map(|reward| Asset {
+            denom: reward.denom.clone(),
+            amount: reward.pending_rewards,
+        })
+        .collect();
+
+    let updated = simulate_holder(config, state, &holder)?;
+
     Ok(HolderResponse {
-        address: address,
-        balance: holder.balance,
-        index: holder.index,
-        pending_rewards: holder.pending_rewards,
-        dec_rewards: holder.dec_rewards,
+        address,
+        balance: updated.balance,
+        rewards: updated.rewards,
+        settled_rewards,
+        auto_compound: updated.auto_compound,
     })
 }
 
-// calculate the reward with decimal
-pub fn get_decimals(value: Decimal256) -> StdResult<Decimal256> {
-    let stringed: &str = &*value.to_string();
-    let parts: &[&str] = &*stringed.split('.').collect::<Vec<&str>>();
-    match parts.len() {
-        1 => Ok(Decimal256::zero()),
-        2 => {
-            let decimals: Decimal256 = Decimal256::from_str(&*("0.".to_owned() + parts[1]))?;
-            Ok(decimals)
+// check that cw20 contract addresses are valid
+pub fn validate_denom(deps: Deps, denom: Denom) -> StdResult<Denom> {
+    match denom {
+        Denom::Native(denom) => Ok(Denom::Native(denom)),
+        Denom::Cw20(addr) => Ok(Denom::Cw20(deps.api.addr_validate(addr.as_str())?)),
+    }
+}
+
+// check that only the native denom is sent, cw20 tokens must be sent through Receive
+pub fn must_pay_native(info: &MessageInfo, denom: &Denom) -> Result<Uint128, ContractError> {
+    match denom {
+        Denom::Native(denom) => Ok(must_pay(info, denom)?),
+        Denom::Cw20(_) => Err(ContractError::InvalidToken {}),
+    }
+}
+
+// collect the native funds sent, zero amounts are ignored
+pub fn native_funds(info: &MessageInfo) -> Result<Vec<Asset>, ContractError> {
+    let funds: Vec<Asset> = info
+        .funds
+        .iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .map(|coin| Asset {
+            denom: Denom::Native(coin.denom.clone()),
+            amount: coin.amount,
+        })
+        .collect();
+    if funds.is_empty() {
+        return Err(PaymentError::NoFunds {}.into());
+    }
+    Ok(funds)
+}
+
+// amounts of each tracked token owed to holders: the bonded and unbonding stake and the
+// rewards not claimed yet
+pub fn obligations(config: &Config, state: &State) -> StdResult<Vec<Asset>> {
+    let mut obligations = vec![Asset {
+        denom: config.staked_token_denom.clone(),
+        amount: state.total_staked.checked_add(state.total_unbonding)?,
+    }];
+    for reward in state.rewards.iter() {
+        let owed = reward.total_rewards.checked_sub(reward.rewards_claimed)?;
+        // the staked token is also a reward denom when compounding is allowed
+        match obligations
+            .iter_mut()
+            .find(|obligation| obligation.denom == reward.denom)
+        {
+            Some(obligation) => obligation.amount = obligation.amount.checked_add(owed)?,
+            None => obligations.push(Asset {
+                denom: reward.denom.clone(),
+                amount: owed,
+            }),
+        }
+    }
+    Ok(obligations)
+}
+
+// amount of the denom owed to holders
+pub fn obligation_of(obligations: &[Asset], denom: &Denom) -> Uint128 {
+    obligations
+        .iter()
+        .find(|obligation| &obligation.denom == denom)
+        .map(|obligation| obligation.amount)
+        .unwrap_or_default()
+}
+
+// query the balance of a native or cw20 token
+pub fn query_token_balance(deps: Deps, denom: &Denom, address: &Addr) -> StdResult<Uint128> {
+    match denom {
+        Denom::Native(denom) => Ok(deps.querier.query_balance(address, denom)?.amount),
+        Denom::Cw20(addr) => {
+            let res: BalanceResponse = deps.querier.query_wasm_smart(
+                addr,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )?;
+            Ok(res.balance)
         }
-        _ => Err(StdError::generic_err("Unexpected number of dots")),
     }
 }
 
+// build the messages sending the assets to the recipient, native coins are sent in one bank message
+pub fn send_assets(recipient: &Addr, assets: &[Asset]) -> StdResult<Vec<CosmosMsg>> {
+    let mut coins: Vec<Coin> = vec![];
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for asset in assets.iter().filter(|asset| !asset.amount.is_zero()) {
+        match &asset.denom {
+            Denom::Native(denom) => coins.push(Coin {
+                denom: denom.to_string(),
+                amount: asset.amount,
+            }),
+            Denom::Cw20(_) => msgs.push(send_tokens(recipient, &asset.denom, asset.amount)?),
+        }
+    }
+    if !coins.is_empty() {
+        // bank expects coins sorted by denom
+        coins.sort_by(|a, b| a.denom.cmp(&b.denom));
+        msgs.insert(
+            0,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: coins,
+            }),
+        );
+    }
+    Ok(msgs)
+}
+
+// build the messages executing msg on the contract with the assets, native coins are attached
+// as funds to one call and every cw20 token is sent with the msg through its Send hook
+pub fn call_with_assets(
+    contract: &Addr,
+    msg: Binary,
+    assets: &[Asset],
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut coins: Vec<Coin> = vec![];
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for asset in assets.iter().filter(|asset| !asset.amount.is_zero()) {
+        match &asset.denom {
+            Denom::Native(denom) => coins.push(Coin {
+                denom: denom.to_string(),
+                amount: asset.amount,
+            }),
+            Denom::Cw20(addr) => msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: addr.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: contract.to_string(),
+                    amount: asset.amount,
+                    msg: msg.clone(),
+                })?,
+                funds: vec![],
+            })),
+        }
+    }
+    if !coins.is_empty() {
+        // funds must be sorted by denom
+        coins.sort_by(|a, b| a.denom.cmp(&b.denom));
+        msgs.insert(
+            0,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract.to_string(),
+                msg,
+                funds: coins,
+            }),
+        );
+    }
+    Ok(msgs)
+}
+
+// build the message sending amount of denom to the recipient
+pub fn send_tokens(recipient: &Addr, denom: &Denom, amount: Uint128) -> StdResult<CosmosMsg> {
+    let msg = match denom {
+        Denom::Native(denom) => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: denom.to_string(),
+                amount,
+            }],
+        }),
+        Denom::Cw20(addr) => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }),
+    };
+    Ok(msg)
+}
+
+pub fn denom_to_string(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => denom.to_string(),
+        Denom::Cw20(addr) => addr.to_string(),
+    }
+}
+
+pub fn operations_to_string(operations: &[Operation]) -> String {
+    operations
+        .iter()
+        .map(|operation| operation.as_str())
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
+pub fn assets_to_string(assets: &[Asset]) -> String {
+    assets
+        .iter()
+        .map(|asset| format!("{}{}", asset.amount, denom_to_string(&asset.denom)))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
 const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
 //query all holders list
 pub fn query_holders(
     deps: Deps,
-    _env: Env,
+    env: Env,
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> StdResult<HoldersResponse> {
     let addr = maybe_addr(deps.api, start_after)?;
     let start = addr.as_ref().map(Bound::exclusive);
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let config = CONFIG.load(deps.storage)?;
+    let state = current_state(deps, &env)?;
     let holders: StdResult<Vec<HolderResponse>> = HOLDERS
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
             let (addr, holder) = item?;
-            let holder_response = HolderResponse {
-                address: addr.to_string(),
-                balance: holder.balance,
-                index: holder.index,
-                pending_rewards: holder.pending_rewards,
-                dec_rewards: holder.dec_rewards,
-            };
-            Ok(holder_response)
+            holder_response(&config, &state, addr.to_string(), holder)
         })
         .collect();
 
     Ok(HoldersResponse { holders: holders? })
 }
 
+pub fn query_reward_denoms(deps: Deps) -> StdResult<RewardDenomsResponse> {
+    let state = STATE.load(deps.storage)?;
+
+    Ok(RewardDenomsResponse {
+        reward_denoms: state
+            .rewards
+            .into_iter()
+            .filter(|reward| reward.active)
+            .map(|reward| reward.denom)
+            .collect(),
+    })
+}
+
+pub fn query_solvency(deps: Deps, env: Env) -> StdResult<SolvencyResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = current_state(deps, &env)?;
+
+    let mut assets = vec![AssetSolvency {
+        denom: config.staked_token_denom,
+        balance: Uint128::zero(),
+        principal: state.total_staked.checked_add(state.total_unbonding)?,
+        owed_rewards: Uint128::zero(),
+        dust: Uint128::zero(),
+        surplus: Uint128::zero(),
+        deficit: Uint128::zero(),
+    }];
+    for reward in state.rewards.iter() {
+        let dust = floor_with_remainder(reward.dust)?.0;
+        let owed_rewards = reward
+            .total_rewards
+            .checked_sub(reward.rewards_claimed)?
+            .checked_sub(dust)?;
+        match assets.iter_mut().find(|asset| asset.denom == reward.denom) {
+            Some(asset) => {
+                asset.owed_rewards = asset.owed_rewards.checked_add(owed_rewards)?;
+                asset.dust = asset.dust.checked_add(dust)?;
+            }
+            None => assets.push(AssetSolvency {
+                denom: reward.denom.clone(),
+                balance: Uint128::zero(),
+                principal: Uint128::zero(),
+                owed_rewards,
+                dust,
+                surplus: Uint128::zero(),
+                deficit: Uint128::zero(),
+            }),
+        }
+    }
+
+    // native balances come from a single query, untracked native tokens are all surplus
+    let native_balances = deps.querier.query_all_balances(&env.contract.address)?;
+    for coin in native_balances.iter() {
+        let denom = Denom::Native(coin.denom.clone());
+        if !assets.iter().any(|asset| asset.denom == denom) {
+            assets.push(AssetSolvency {
+                denom,
+                balance: Uint128::zero(),
+                principal: Uint128::zero(),
+                owed_rewards: Uint128::zero(),
+                dust: Uint128::zero(),
+                surplus: Uint128::zero(),
+                deficit: Uint128::zero(),
+            });
+        }
+    }
+    for asset in assets.iter_mut() {
+        asset.balance = match &asset.denom {
+            Denom::Native(denom) => native_balances
+                .iter()
+                .find(|coin| &coin.denom == denom)
+                .map(|coin| coin.amount)
+                .unwrap_or_default(),
+            Denom::Cw20(_) => query_token_balance(deps, &asset.denom, &env.contract.address)?,
+        };
+        let owed = asset
+            .principal
+            .checked_add(asset.owed_rewards)?
+            .checked_add(asset.dust)?;
+        asset.surplus = asset.balance.saturating_sub(owed);
+        asset.deficit = owed.saturating_sub(asset.balance);
+    }
+
+    Ok(SolvencyResponse {
+        solvent: assets.iter().all(|asset| asset.deficit.is_zero()),
+        assets,
+    })
+}
+
+pub fn query_reward_recipient(deps: Deps, address: String) -> StdResult<RewardRecipientResponse> {
+    let addr = deps.api.addr_validate(address.as_str())?;
+    Ok(RewardRecipientResponse {
+        recipient: reward_recipient(deps, &addr)?.into_string(),
+    })
+}
+
+pub fn query_operators(deps: Deps, env: Env, owner: String) -> StdResult<OperatorsResponse> {
+    let owner = deps.api.addr_validate(owner.as_str())?;
+    let operators: StdResult<Vec<Operator>> = OPERATORS
+        .prefix(&owner)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(_, expires)| !expires.is_expired(&env.block))
+        })
+        .map(|item| {
+            let (operator, expires) = item?;
+            Ok(Operator {
+                operator: operator.into_string(),
+                expires,
+            })
+        })
+        .collect();
+
+    Ok(OperatorsResponse {
+        operators: operators?,
+    })
+}
+
+pub fn query_staked_balance_at_height(
+    deps: Deps,
+    env: Env,
+    address: String,
+    height: Option<u64>,
+) -> StdResult<StakedBalanceAtHeightResponse> {
+    let addr = deps.api.addr_validate(address.as_str())?;
+    let height = height.unwrap_or(env.block.height);
+    let balance = STAKED_BALANCES
+        .may_load_at_height(deps.storage, &addr, height)?
+        .unwrap_or_default();
+    Ok(StakedBalanceAtHeightResponse { balance, height })
+}
+
+pub fn query_total_staked_at_height(
+    deps: Deps,
+    env: Env,
+    height: Option<u64>,
+) -> StdResult<TotalStakedAtHeightResponse> {
+    let height = height.unwrap_or(env.block.height);
+    let total = STAKED_TOTAL
+        .may_load_at_height(deps.storage, height)?
+        .unwrap_or_default();
+    Ok(TotalStakedAtHeightResponse { total, height })
+}
+
+pub fn query_voting_power_at_height(
+    deps: Deps,
+    env: Env,
+    address: String,
+    height: Option<u64>,
+) -> StdResult<VotingPowerAtHeightResponse> {
+    let res = query_staked_balance_at_height(deps, env, address, height)?;
+    Ok(VotingPowerAtHeightResponse {
+        power: res.balance,
+        height: res.height,
+    })
+}
+
+pub fn query_total_power_at_height(
+    deps: Deps,
+    env: Env,
+    height: Option<u64>,
+) -> StdResult<TotalPowerAtHeightResponse> {
+    let res = query_total_staked_at_height(deps, env, height)?;
+    Ok(TotalPowerAtHeightResponse {
+        power: res.total,
+        height: res.height,
+    })
+}
+
+pub fn query_info(deps: Deps) -> StdResult<InfoResponse> {
+    let info = cw2::get_contract_version(deps.storage)?;
+    Ok(InfoResponse { info })
+}
+
+pub fn query_claims(deps: Deps, address: String) -> StdResult<ClaimsResponse> {
+    let addr = deps.api.addr_validate(address.as_str())?;
+    CLAIMS.query_claims(deps, &addr)
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let new_version: Version = CONTRACT_VERSION.parse()?;
+
+    // v0.1 never stored its version
+    let previous_version = match cw2::CONTRACT.may_load(deps.storage)? {
+        Some(info) => {
+            if info.contract != CONTRACT_NAME {
+                return Err(ContractError::CannotMigrate {
+                    previous_contract: info.contract,
+                });
+            }
+            info.version.parse()?
+        }
+        None => Version::new(0, 1, 0),
+    };
+    if previous_version > new_version {
+        return Err(ContractError::CannotDowngrade {
+            previous_version: previous_version.to_string(),
+            new_version: new_version.to_string(),
+        });
+    }
+
+    if previous_version < Version::new(0, 2, 0) {
+        let pauser = maybe_addr(deps.api, msg.pauser.clone())?;
+        migrate_v0_1(deps.storage, env.block.height, pauser, msg)?;
+    }
+
+    cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("previous_version", previous_version.to_string())
+        .add_attribute("new_version", new_version.to_string()))
 }
@@ -39,4 +39,79 @@ pub enum ContractError {
 
     #[error("Stake denom and reward denom cannot be same")]
     SameDenom {},
+
+    #[error("No claims that can be released currently")]
+    NothingToClaim {},
+
+    #[error("Received token is not accepted")]
+    InvalidToken {},
+
+    #[error("{0} is not a reward denom")]
+    NotRewardDenom(String),
+
+    #[error("Reward denom is listed more than once")]
+    DuplicateDenom {},
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version {previous_version} to {new_version}")]
+    CannotDowngrade {
+        previous_version: String,
+        new_version: String,
+    },
+
+    #[error("Semver parsing error: {0}")]
+    SemVer(String),
+
+    #[error("No pending admin")]
+    NoPendingAdmin {},
+
+    #[error("Admin proposal expired")]
+    AdminProposalExpired {},
+
+    #[error("Operation is paused")]
+    Paused {},
+
+    #[error("No surplus to withdraw")]
+    NoSurplus {},
+
+    #[error("Contract is in emergency mode")]
+    EmergencyMode {},
+
+    #[error("Contract is not in emergency mode")]
+    NotEmergencyMode {},
+
+    #[error("Reward duration must be greater than zero and at most ten years")]
+    InvalidRewardDuration {},
+
+    #[error("Reward denom still has rewards to distribute")]
+    UndistributedRewards {},
+
+    #[error("No dust to redistribute")]
+    NoDust {},
+
+    #[error("Compounding is not allowed")]
+    CompoundingDisabled {},
+
+    #[error("Rewards can't be sent to a recipient and a contract call at once")]
+    RecipientAndCall {},
+
+    #[error("Rewards can't be routed to this contract, the staked token or a reward token")]
+    InvalidCallTarget {},
+
+    #[error("Stake transfers are disabled")]
+    TransfersDisabled {},
+
+    #[error("Cannot transfer stake to yourself")]
+    SelfTransfer {},
+
+    #[error("Approval expired")]
+    Expired {},
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
@@ -1,6 +1,8 @@
 pub use crate::error::ContractError;
 pub mod contract;
 mod error;
+pub mod math;
+pub mod migrations;
 pub mod msg;
 pub mod state;
 #[cfg(test)]
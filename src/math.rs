@@ -1,81 +1,177 @@
-use cosmwasm_std::Decimal;
-
-/// return a * b
-pub fn decimal_multiplication_in_256(a: Decimal, b: Decimal) -> Decimal {
-    let a_u256: Decimal256 = a.into();
-    let b_u256: Decimal256 = b.into();
-    let c_u256: Decimal = (b_u256 * a_u256).into();
-    c_u256
+//! Reward index arithmetic shared by the contract and off-chain integrators.
+//!
+//! Indexes are rewards per staked token scaled by [`INDEX_SCALE`]. Every division returns its
+//! remainder so callers can carry it over to the next update instead of rounding it away.
+
+use cosmwasm_std::{StdError, StdResult, Uint128, Uint256};
+use std::convert::TryFrom;
+
+use crate::state::INDEX_SCALE;
+
+/// Returns `amount * INDEX_SCALE`
+pub fn scale(amount: Uint128) -> StdResult<Uint256> {
+    Ok(Uint256::from(amount).checked_mul(Uint256::from(INDEX_SCALE))?)
 }
 
-/// return a + b
-pub fn decimal_summation_in_256(a: Decimal, b: Decimal) -> Decimal {
-    let a_u256: Decimal256 = a.into();
-    let b_u256: Decimal256 = b.into();
-    let c_u256: Decimal = (b_u256 + a_u256).into();
-    c_u256
+/// Returns the global index increase of distributing `scaled_amount` plus the previous
+/// `remainder` over `total_staked`, and the new remainder
+pub fn index_delta(
+    scaled_amount: Uint256,
+    remainder: Uint256,
+    total_staked: Uint128,
+) -> StdResult<(Uint256, Uint256)> {
+    let scaled_amount = scaled_amount.checked_add(remainder)?;
+    let total_staked = Uint256::from(total_staked);
+    Ok((
+        scaled_amount.checked_div(total_staked)?,
+        scaled_amount.checked_rem(total_staked)?,
+    ))
 }
 
-/// return a - b
-pub fn decimal_subtraction_in_256(a: Decimal, b: Decimal) -> Decimal {
-    let a_u256: Decimal256 = a.into();
-    let b_u256: Decimal256 = b.into();
-    let c_u256: Decimal = (a_u256 - b_u256).into();
-    c_u256
+/// Returns the scaled rewards accrued by `balance` since `holder_index`, including the
+/// fractional `dec_rewards` carried over from the previous update
+pub fn accrual(
+    balance: Uint128,
+    global_index: Uint256,
+    holder_index: Uint256,
+    dec_rewards: Uint256,
+) -> StdResult<Uint256> {
+    let index_diff = global_index.checked_sub(holder_index)?;
+    Ok(Uint256::from(balance)
+        .checked_mul(index_diff)?
+        .checked_add(dec_rewards)?)
+}
+
+/// Splits scaled rewards into the whole amount and the scaled fractional remainder
+pub fn floor_with_remainder(scaled_amount: Uint256) -> StdResult<(Uint128, Uint256)> {
+    let scale = Uint256::from(INDEX_SCALE);
+    Ok((
+        Uint128::try_from(scaled_amount.checked_div(scale)?)?,
+        scaled_amount.checked_rem(scale)?,
+    ))
+}
+
+/// Returns the share of `amount` owned by `share` out of `total`, rounded down
+pub fn pro_rata(amount: Uint128, share: Uint128, total: Uint128) -> StdResult<Uint128> {
+    amount
+        .checked_multiply_ratio(share, total)
+        .map_err(|err| StdError::generic_err(err.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cosmwasm_std::{Decimal, Uint128};
 
     #[test]
-    fn test_decimal_multiplication() {
-        let a = Uint128::new(100);
-        let b = Decimal::from_ratio(Uint128::new(1111111), Uint128::new(10000000));
-        let multiplication =
-            decimal_multiplication_in_256(Decimal::from_ratio(a, Uint128::new(1)), b);
-        assert_eq!(multiplication.to_string(), "11.11111");
+    fn test_scale() {
+        assert_eq!(
+            scale(Uint128::new(3)).unwrap(),
+            Uint256::from(3 * INDEX_SCALE)
+        );
     }
 
     #[test]
-    fn test_decimal_sumation() {
-        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
-        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
-        let res = decimal_summation_in_256(a, b);
-        assert_eq!(res.to_string(), "0.6");
+    fn test_index_delta() {
+        // 100 rewards over 3 staked tokens leave a scaled remainder
+        let (delta, remainder) = index_delta(
+            scale(Uint128::new(100)).unwrap(),
+            Uint256::zero(),
+            Uint128::new(3),
+        )
+        .unwrap();
+        assert_eq!(delta, Uint256::from(33_333_333_333_333_333_333u128));
+        assert_eq!(remainder, Uint256::from(1u128));
+
+        // the remainder is distributed with the next increase
+        let (delta, remainder) =
+            index_delta(Uint256::from(2u128), remainder, Uint128::new(3)).unwrap();
+        assert_eq!(delta, Uint256::from(1u128));
+        assert_eq!(remainder, Uint256::zero());
+
+        // nothing staked
+        index_delta(Uint256::one(), Uint256::zero(), Uint128::zero()).unwrap_err();
     }
 
     #[test]
-    fn test_decimal_subtraction() {
-        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
-        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
-        let res = decimal_subtraction_in_256(a, b);
-        assert_eq!(res.to_string(), "0.2");
+    fn test_accrual() {
+        let global_index = Uint256::from(5 * INDEX_SCALE / 2);
+        let holder_index = Uint256::from(INDEX_SCALE);
+        let scaled = accrual(
+            Uint128::new(10),
+            global_index,
+            holder_index,
+            Uint256::from(7u128),
+        )
+        .unwrap();
+        assert_eq!(scaled, Uint256::from(15 * INDEX_SCALE + 7));
+
+        // holder index can never be ahead of the global index
+        accrual(
+            Uint128::new(10),
+            holder_index,
+            global_index,
+            Uint256::zero(),
+        )
+        .unwrap_err();
     }
 
     #[test]
-    fn test_decimal_multiplication_in_256() {
-        let a = Uint128::new(100);
-        let b = Decimal::from_ratio(Uint128::new(1111111), Uint128::new(10000000));
-        let multiplication =
-            decimal_multiplication_in_256(Decimal::from_ratio(a, Uint128::new(1)), b);
-        assert_eq!(multiplication.to_string(), "11.11111");
+    fn test_floor_with_remainder() {
+        let (amount, remainder) =
+            floor_with_remainder(Uint256::from(15 * INDEX_SCALE + 7)).unwrap();
+        assert_eq!(amount, Uint128::new(15));
+        assert_eq!(remainder, Uint256::from(7u128));
+
+        let (amount, remainder) = floor_with_remainder(Uint256::from(7u128)).unwrap();
+        assert_eq!(amount, Uint128::zero());
+        assert_eq!(remainder, Uint256::from(7u128));
     }
 
     #[test]
-    fn test_decimal_sumation_in_256() {
-        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
-        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
-        let res = decimal_summation_in_256(a, b);
-        assert_eq!(res.to_string(), "0.6");
+    fn test_pro_rata() {
+        assert_eq!(
+            pro_rata(Uint128::new(100), Uint128::new(1), Uint128::new(3)).unwrap(),
+            Uint128::new(33)
+        );
+        assert_eq!(
+            pro_rata(Uint128::new(100), Uint128::new(3), Uint128::new(3)).unwrap(),
+            Uint128::new(100)
+        );
+        pro_rata(Uint128::new(100), Uint128::new(1), Uint128::zero()).unwrap_err();
     }
 
     #[test]
-    fn test_decimal_subtraction_in_256() {
-        let a = Decimal::from_ratio(Uint128::new(20), Uint128::new(50));
-        let b = Decimal::from_ratio(Uint128::new(10), Uint128::new(50));
-        let res = decimal_subtraction_in_256(a, b);
-        assert_eq!(res.to_string(), "0.2");
+    fn test_accrual_matches_distribution() {
+        // holders of 1 and 2 tokens share 100 rewards, the last one is left as fractions
+        let total_staked = Uint128::new(3);
+        let (global_index, _) = index_delta(
+            scale(Uint128::new(100)).unwrap(),
+            Uint256::zero(),
+            total_staked,
+        )
+        .unwrap();
+        let (first, first_dec) = floor_with_remainder(
+            accrual(
+                Uint128::new(1),
+                global_index,
+                Uint256::zero(),
+                Uint256::zero(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let (second, second_dec) = floor_with_remainder(
+            accrual(
+                Uint128::new(2),
+                global_index,
+                Uint256::zero(),
+                Uint256::zero(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(first, Uint128::new(33));
+        assert_eq!(second, Uint128::new(66));
+        assert!(first_dec + second_dec < Uint256::from(INDEX_SCALE));
     }
 }
@@ -0,0 +1,108 @@
+use cosmwasm_std::{Addr, Order, StdResult, Storage, Uint128, Uint256};
+use cw20::Denom;
+
+use crate::msg::MigrateMsg;
+use crate::state::{
+    save_holder, save_state, Config, Holder, HolderReward, RewardState, State, CONFIG, DAO,
+    INDEX_SCALE, MAX_REWARD_DURATION,
+};
+use crate::ContractError;
+
+/// Store layout of v0.1, a single native reward denom without cw2 version info
+pub mod v0_1 {
+    use cosmwasm_std::{Addr, Decimal256, Uint128};
+    use cw_storage_plus::{Item, Map};
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct State {
+        pub global_index: Decimal256,
+        pub total_staked: Uint128,
+        pub total_rewards: Uint128,
+        pub rewards_claimed: Uint128,
+    }
+    pub const STATE: Item<State> = Item::new("state");
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct Config {
+        pub staked_token_denom: String,
+        pub reward_denom: String,
+        pub admin: Addr,
+    }
+    pub const CONFIG: Item<Config> = Item::new("config");
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct Holder {
+        pub balance: Uint128,
+        pub index: Decimal256,
+        pub dec_rewards: Decimal256,
+        pub pending_rewards: Uint128,
+    }
+    pub const HOLDERS: Map<&Addr, Holder> = Map::new("holders");
+}
+
+// rewrite the v0.1 config, state and holders in the current layout, balances are snapshotted
+// from the migration height on
+pub fn migrate_v0_1(
+    storage: &mut dyn Storage,
+    height: u64,
+    pauser: Option<Addr>,
+    msg: MigrateMsg,
+) -> Result<(), ContractError> {
+    if matches!(msg.reward_duration, Some(duration) if duration == 0 || duration > MAX_REWARD_DURATION)
+    {
+        return Err(ContractError::InvalidRewardDuration {});
+    }
+
+    let old_config = v0_1::CONFIG.load(storage)?;
+    let old_state = v0_1::STATE.load(storage)?;
+    let reward_denom = Denom::Native(old_config.reward_denom);
+
+    let config = Config {
+        staked_token_denom: Denom::Native(old_config.staked_token_denom),
+        admin: Some(old_config.admin.clone()),
+        unbonding_period: msg.unbonding_period,
+        reward_duration: msg.reward_duration,
+        pauser,
+        paused: vec![],
+        allow_compounding: false,
+        transfers_enabled: false,
+    };
+    CONFIG.save(storage, &config)?;
+    // the v0.1 admin is the DAO that deployed the contract
+    DAO.save(storage, &old_config.admin)?;
+
+    let mut reward = RewardState::new(reward_denom.clone());
+    // v0.1 decimals have the same 18 digit precision as INDEX_SCALE
+    let scale = Uint256::from(INDEX_SCALE);
+    reward.global_index = old_state.global_index * scale;
+    reward.total_rewards = old_state.total_rewards;
+    reward.rewards_claimed = old_state.rewards_claimed;
+    let state = State {
+        total_staked: old_state.total_staked,
+        total_unbonding: Uint128::zero(),
+        rewards: vec![reward],
+        emergency: false,
+    };
+    save_state(storage, height, &state)?;
+
+    let old_holders = v0_1::HOLDERS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (addr, old_holder) in old_holders {
+        let holder = Holder {
+            balance: old_holder.balance,
+            rewards: vec![HolderReward {
+                denom: reward_denom.clone(),
+                index: old_holder.index * scale,
+                dec_rewards: old_holder.dec_rewards * scale,
+                pending_rewards: old_holder.pending_rewards,
+            }],
+            auto_compound: false,
+        };
+        save_holder(storage, height, &addr, &holder)?;
+    }
+
+    Ok(())
+}
@@ -1,30 +1,161 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Decimal256, Uint128};
+use cosmwasm_std::{Binary, Uint128, Uint256};
+use cw2::ContractVersion;
+use cw20::{Cw20ReceiveMsg, Denom};
+use cw_utils::{Duration, Expiration};
+
+use crate::state::{HolderReward, Operation, RewardState};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub staked_token_denom: String,
-    pub reward_denom: String,
+    /// Native denom or cw20 contract address of the staked token
+    pub staked_token_denom: Denom,
+    /// Native denoms or cw20 contract addresses of the distributed reward tokens
+    pub reward_denoms: Vec<Denom>,
     pub admin: Option<String>,
+    /// Withdrawn stake is locked for this period before it can be claimed
+    pub unbonding_period: Option<Duration>,
+    /// Deposited rewards are streamed linearly over this many seconds
+    pub reward_duration: Option<u64>,
+    /// Address allowed to pause operations besides the admin
+    pub pauser: Option<String>,
+    /// Allow the staked token as a reward denom, its rewards can be compounded into stake
+    pub allow_compounding: bool,
+    /// Allow holders to transfer stake to each other without unbonding
+    pub transfers_enabled: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    /// Distribute the attached native rewards, any number of reward denoms can be sent at once
     UpdateReward {},
 
-    UpdateHolderReward { address: Option<String> },
-    BondStake {},
+    UpdateHolderReward {
+        address: Option<String>,
+    },
+    /// Bond the sent stake for the recipient, the sender if not set
+    BondStake {
+        recipient: Option<String>,
+    },
+
+    /// Bond cw20 stake or deposit cw20 rewards through the Cw20 Send hook
+    Receive(Cw20ReceiveMsg),
+
+    /// Withdraw stake of the sender or of the owner that approved the sender as operator
+    WithdrawStake {
+        amount: Option<Uint128>,
+        owner: Option<String>,
+    },
+
+    /// Claim stake whose unbonding period is over
+    Claim {},
 
-    WithdrawStake { amount: Option<Uint128> },
+    /// Claim pending rewards to the recipient, the holder's reward recipient or the holder.
+    /// With a call, the rewards are sent along with it to the target contract instead.
+    /// Operators claim for the owner to the owner's reward recipient
+    ReceiveReward {
+        recipient: Option<String>,
+        call: Option<ContractCall>,
+        owner: Option<String>,
+    },
+
+    /// Set or unset the address claimed rewards are sent to by default
+    SetRewardRecipient {
+        address: Option<String>,
+    },
+
+    /// Withdraw the tokens not owed to holders, admin only
+    AdminWithdrawSurplus {},
+
+    /// Withdraw any tokens and switch to emergency mode, admin only
+    AdminEmergencyWithdraw {
+        assets: Vec<Asset>,
+    },
 
-    ReceiveReward {},
+    /// Exit with a pro rata share of the remaining tokens in emergency mode
+    EmergencyExit {},
 
-    AdminWithdrawAll {},
+    /// Propose a new admin that has to accept before the optional expiry, admin only
+    ProposeAdmin {
+        address: String,
+        expiry: Option<Expiration>,
+    },
+
+    /// Become the admin, pending admin only
+    AcceptAdmin {},
+
+    /// Cancel the pending admin proposal, admin only
+    CancelAdminProposal {},
+
+    /// Give up admin control for good, admin only
+    RenounceAdmin {},
+
+    /// Distribute reward tokens sent to the contract without UpdateReward, e.g. plain bank transfers
+    SyncRewards {},
+
+    /// Pause operations, admin or pauser only. Withdrawals stay open unless paused explicitly
+    Pause {
+        operations: Vec<Operation>,
+    },
+
+    /// Unpause operations, admin or pauser only
+    Unpause {
+        operations: Vec<Operation>,
+    },
+
+    /// Set or unset the pauser, admin only
+    UpdatePauser {
+        address: Option<String>,
+    },
 
-    UpdateAdmin{address: String},
+    /// Start distributing a new reward denom, admin only
+    AddRewardDenom {
+        denom: Denom,
+    },
+
+    /// Stop accepting deposits of a reward denom once all its deposits are distributed, admin only
+    RemoveRewardDenom {
+        denom: Denom,
+    },
+
+    /// Distribute the fractional rewards left behind by exited holders to the current stakers
+    RedistributeDust {},
+
+    /// Bond the pending rewards of the staked token of the sender or of the owner that approved
+    /// the sender as operator
+    Compound {
+        owner: Option<String>,
+    },
+
+    /// Compound the rewards of the staked token on every update of the holder
+    SetAutoCompound {
+        enabled: bool,
+    },
+
+    /// Move bonded stake to the recipient without unbonding, rewards of both are settled first
+    TransferStake {
+        recipient: String,
+        amount: Uint128,
+    },
+
+    /// Enable or disable stake transfers, admin only
+    SetTransfersEnabled {
+        enabled: bool,
+    },
+
+    /// Allow the operator to claim, compound and withdraw on behalf of the sender until it expires
+    ApproveOperator {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+
+    /// Remove the approval of the operator
+    RevokeOperator {
+        operator: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -33,7 +164,9 @@ pub enum ReceiveMsg {
     /// Bond stake user staking balance
     /// Withdraw rewards to pending rewards
     /// Set current reward index to global index
-    BondStake {},
+    /// The stake is bonded for the recipient, the cw20 sender if not set
+    BondStake { recipient: Option<String> },
+    /// Distribute the received cw20 rewards to holders
     UpdateRewardIndex {},
 }
 
@@ -52,34 +185,141 @@ pub enum QueryMsg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    Claims {
+        address: String,
+    },
+    RewardDenoms {},
+    /// Address the rewards of the holder are sent to by default
+    RewardRecipient {
+        address: String,
+    },
+    /// Operators approved by the owner that did not expire
+    Operators {
+        owner: String,
+    },
+    /// Bonded balance of the address at the start of the block, the current block if not set
+    StakedBalanceAtHeight {
+        address: String,
+        height: Option<u64>,
+    },
+    /// Total bonded stake at the start of the block, the current block if not set
+    TotalStakedAtHeight {
+        height: Option<u64>,
+    },
+    /// DAO DAO voting module interface, the voting power is the bonded balance
+    VotingPowerAtHeight {
+        address: String,
+        height: Option<u64>,
+    },
+    /// DAO DAO voting module interface, the total power is the total bonded stake
+    TotalPowerAtHeight {
+        height: Option<u64>,
+    },
+    /// DAO DAO voting module interface, cw2 name and version of the contract
+    Info {},
+    /// DAO DAO voting module interface, address of the DAO
+    Dao {},
+    /// Balance of every token held split into what is owed and the surplus or deficit
+    Solvency {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StateResponse {
-    pub global_index: Decimal256,
     pub total_staked: Uint128,
-    pub total_rewards: Uint128,
-    pub rewards_claimed: Uint128,
+    pub total_unbonding: Uint128,
+    pub rewards: Vec<RewardState>,
+    pub emergency: bool,
 }
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
-    pub staked_token_denom: String,
-    pub reward_denom: String,
-    pub admin: String,
+    pub staked_token_denom: Denom,
+    pub admin: Option<String>,
+    pub pending_admin: Option<String>,
+    pub pending_admin_expiry: Option<Expiration>,
+    pub unbonding_period: Option<Duration>,
+    pub reward_duration: Option<u64>,
+    pub pauser: Option<String>,
+    pub paused: Vec<Operation>,
+    pub allow_compounding: bool,
+    pub transfers_enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardDenomsResponse {
+    pub reward_denoms: Vec<Denom>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardRecipientResponse {
+    pub recipient: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InfoResponse {
+    pub info: ContractVersion,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakedBalanceAtHeightResponse {
+    pub balance: Uint128,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalStakedAtHeightResponse {
+    pub total: Uint128,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OperatorsResponse {
+    pub operators: Vec<Operator>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Operator {
+    pub operator: String,
+    pub expires: Expiration,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AccruedRewardsResponse {
-    pub rewards: Uint128,
+    pub rewards: Vec<AccruedReward>,
+}
+
+/// Rewards of a denom as if the holder was updated at the current block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccruedReward {
+    pub denom: Denom,
+    /// Amount the holder can claim
+    pub claimable: Uint128,
+    /// Fractional reward scaled by INDEX_SCALE, carried over to the next update
+    pub dec_rewards: Uint256,
+    /// Part of the claimable amount already settled in the store
+    pub settled: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct HolderResponse {
     pub address: String,
     pub balance: Uint128,
-    pub index: Decimal256,
-    pub pending_rewards: Uint128,
-    pub dec_rewards: Decimal256,
+    /// Rewards as if the holder was updated at the current block
+    pub rewards: Vec<HolderReward>,
+    /// Pending rewards already settled in the store
+    pub settled_rewards: Vec<Asset>,
+    pub auto_compound: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -88,4 +328,47 @@ pub struct HoldersResponse {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct MigrateMsg {}
+pub struct SolvencyResponse {
+    /// Whether every token covers what is owed
+    pub solvent: bool,
+    pub assets: Vec<AssetSolvency>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetSolvency {
+    pub denom: Denom,
+    pub balance: Uint128,
+    /// Bonded and unbonding stake
+    pub principal: Uint128,
+    /// Rewards distributed or waiting to be distributed that holders can still claim
+    pub owed_rewards: Uint128,
+    /// Whole rewards left behind as fractions by exited holders
+    pub dust: Uint128,
+    /// Balance above the principal, owed rewards and dust
+    pub surplus: Uint128,
+    /// Amount missing to cover the principal, owed rewards and dust
+    pub deficit: Uint128,
+}
+
+/// Options for the config fields added since v0.1, only used when migrating a v0.1 store
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    pub unbonding_period: Option<Duration>,
+    pub reward_duration: Option<u64>,
+    pub pauser: Option<String>,
+}
+
+/// Execute message on a contract, claimed native rewards are attached as funds and cw20
+/// rewards are sent with the message through the cw20 Send hook
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractCall {
+    pub contract_addr: String,
+    pub msg: Binary,
+}
+
+/// Amount of a native or cw20 token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Asset {
+    pub denom: Denom,
+    pub amount: Uint128,
+}
@@ -1,53 +1,271 @@
-use cosmwasm_std::{Addr, Decimal256, Uint128};
+use cosmwasm_std::{Addr, StdResult, Storage, Uint128, Uint256};
 
+use cw20::Denom;
 use cw_controllers::Claims;
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Item, Map, SnapshotItem, SnapshotMap, Strategy};
+use cw_utils::{Duration, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+/// Indexes are rewards per staked token scaled by INDEX_SCALE
+pub const INDEX_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// Longest reward duration, ten years in seconds
+pub const MAX_REWARD_DURATION: u64 = 10 * 365 * 24 * 60 * 60;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
-    pub global_index: Decimal256,
     pub total_staked: Uint128,
+    /// Withdrawn stake locked in CLAIMS
+    pub total_unbonding: Uint128,
+    /// Distribution state of every reward denom
+    pub rewards: Vec<RewardState>,
+    /// Set once the admin made an emergency withdraw, holders can only exit pro rata
+    pub emergency: bool,
+}
+pub const STATE: Item<State> = Item::new("state");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardState {
+    /// Native denom or cw20 contract address of the reward token
+    pub denom: Denom,
+    pub global_index: Uint256,
+    /// Scaled rewards left over from dividing by the total staked, added to the next increase
+    pub index_remainder: Uint256,
+    /// Fractional rewards scaled by INDEX_SCALE left behind by holders that withdrew everything,
+    /// they can't be claimed until they are redistributed
+    pub dust: Uint256,
     pub total_rewards: Uint128,
     pub rewards_claimed: Uint128,
+    /// Rewards received while nothing was staked, distributed once stake is bonded
+    pub undistributed_rewards: Uint128,
+    /// Removed reward denoms stop accepting deposits but can still be claimed
+    pub active: bool,
+    /// Scaled amount streamed to holders per second when reward_duration is set
+    pub reward_rate: Uint256,
+    /// Time in seconds the current stream ends
+    pub period_finish: u64,
+    /// Time in seconds the global index was last advanced
+    pub last_update_time: u64,
 }
-pub const STATE: Item<State> = Item::new("state");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    pub staked_token_denom: String,
-    pub reward_denom: String,
-    pub admin: Addr,
+    /// Native denom or cw20 contract address of the staked token
+    pub staked_token_denom: Denom,
+    /// No admin actions are possible once the admin is renounced
+    pub admin: Option<Addr>,
+    /// Time withdrawn stake stays locked in CLAIMS before it can be claimed,
+    /// stake is released instantly if not set
+    pub unbonding_period: Option<Duration>,
+    /// Seconds deposited rewards are streamed over, rewards are distributed instantly if not set
+    pub reward_duration: Option<u64>,
+    /// Address allowed to pause and unpause operations besides the admin
+    pub pauser: Option<Addr>,
+    pub paused: Vec<Operation>,
+    /// The staked token can be a reward denom whose rewards are compounded into stake
+    pub allow_compounding: bool,
+    /// Holders can transfer stake to each other without unbonding
+    pub transfers_enabled: bool,
+}
+
+/// Operations that can be paused
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Bond,
+    /// Withdrawing stake and claiming it once unbonded
+    Withdraw,
+    /// Claiming rewards
+    Claim,
+    /// Reward deposits and syncs
+    UpdateReward,
+    /// Stake transfers between holders
+    Transfer,
+    /// Holder updates outside of the other operations
+    UpdateHolder,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// Admin proposed by the current admin, becomes the admin once accepted
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingAdmin {
+    pub address: Addr,
+    pub expiry: Option<Expiration>,
+}
+
+pub const PENDING_ADMIN: Item<PendingAdmin> = Item::new("pending_admin");
+
+// DAO the contract serves as voting module for, the instantiator
+pub const DAO: Item<Addr> = Item::new("dao");
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Holder {
     pub balance: Uint128,
-    pub index: Decimal256,
-    pub dec_rewards: Decimal256,
+    /// Accrued rewards of every reward denom
+    pub rewards: Vec<HolderReward>,
+    /// Rewards of the staked token are compounded into the balance on every update
+    pub auto_compound: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HolderReward {
+    pub denom: Denom,
+    pub index: Uint256,
+    /// Fractional reward scaled by INDEX_SCALE, carried over to the next update
+    pub dec_rewards: Uint256,
     pub pending_rewards: Uint128,
 }
 
-// REWARDS (holder_addr, cw20_addr) -> Holder
+// HOLDERS holder_addr -> Holder
 pub const HOLDERS: Map<&Addr, Holder> = Map::new("holders");
 
+// STAKED_BALANCES holder_addr -> balance, snapshotted by block height
+pub const STAKED_BALANCES: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "staked_balances",
+    "staked_balances__checkpoints",
+    "staked_balances__changelog",
+    Strategy::EveryBlock,
+);
+
+// total staked snapshotted by block height
+pub const STAKED_TOTAL: SnapshotItem<Uint128> = SnapshotItem::new(
+    "staked_total",
+    "staked_total__checkpoints",
+    "staked_total__changelog",
+    Strategy::EveryBlock,
+);
+
+// REWARD_RECIPIENTS holder_addr -> address claimed rewards are sent to
+pub const REWARD_RECIPIENTS: Map<&Addr, Addr> = Map::new("reward_recipients");
+
+// OPERATORS (holder_addr, operator_addr) -> expiration of the approval
+pub const OPERATORS: Map<(&Addr, &Addr), Expiration> = Map::new("operators");
+
+// CLAIMS holder_addr -> unbonding stake
 pub const CLAIMS: Claims = Claims::new("claims");
 
+impl State {
+    pub fn reward_mut(&mut self, denom: &Denom) -> Option<&mut RewardState> {
+        self.rewards
+            .iter_mut()
+            .find(|reward| &reward.denom == denom)
+    }
+}
+
+impl Config {
+    pub fn is_admin(&self, addr: &Addr) -> bool {
+        self.admin.as_ref() == Some(addr)
+    }
+
+    pub fn is_paused(&self, operation: Operation) -> bool {
+        self.paused.contains(&operation)
+    }
+
+    // compounding claims rewards and bonds them, the stake is frozen in emergency mode
+    pub fn is_compounding_paused(&self, state: &State) -> bool {
+        state.emergency || self.is_paused(Operation::Claim) || self.is_paused(Operation::Bond)
+    }
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Bond => "bond",
+            Operation::Withdraw => "withdraw",
+            Operation::Claim => "claim",
+            Operation::UpdateReward => "update_reward",
+            Operation::Transfer => "transfer",
+            Operation::UpdateHolder => "update_holder",
+        }
+    }
+}
+
+impl RewardState {
+    pub fn new(denom: Denom) -> Self {
+        RewardState {
+            denom,
+            global_index: Uint256::zero(),
+            index_remainder: Uint256::zero(),
+            dust: Uint256::zero(),
+            total_rewards: Uint128::zero(),
+            rewards_claimed: Uint128::zero(),
+            undistributed_rewards: Uint128::zero(),
+            active: true,
+            reward_rate: Uint256::zero(),
+            period_finish: 0,
+            last_update_time: 0,
+        }
+    }
+}
+
 impl Holder {
-    pub fn new(
-        balance: Uint128,
-        index: Decimal256,
-        pending_rewards: Uint128,
-        dec_rewards: Decimal256,
-    ) -> Self {
+    /// New holder starts accruing from the current global indexes
+    pub fn new(balance: Uint128, state: &State) -> Self {
         Holder {
             balance,
+            rewards: state
+                .rewards
+                .iter()
+                .map(|reward| HolderReward::new(reward.denom.clone(), reward.global_index))
+                .collect(),
+            auto_compound: false,
+        }
+    }
+
+    /// Returns the holder's reward entry of the denom, creating it if missing
+    pub fn reward_mut(&mut self, denom: &Denom) -> &mut HolderReward {
+        match self
+            .rewards
+            .iter()
+            .position(|reward| &reward.denom == denom)
+        {
+            Some(i) => &mut self.rewards[i],
+            None => {
+                self.rewards
+                    .push(HolderReward::new(denom.clone(), Uint256::zero()));
+                self.rewards.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+impl HolderReward {
+    pub fn new(denom: Denom, index: Uint256) -> Self {
+        HolderReward {
+            denom,
             index,
-            pending_rewards,
-            dec_rewards,
+            dec_rewards: Uint256::zero(),
+            pending_rewards: Uint128::zero(),
         }
     }
 }
+
+// save the holder and snapshot its balance at the height if it changed
+pub fn save_holder(
+    storage: &mut dyn Storage,
+    height: u64,
+    addr: &Addr,
+    holder: &Holder,
+) -> StdResult<()> {
+    HOLDERS.save(storage, addr, holder)?;
+    if STAKED_BALANCES.may_load(storage, addr)?.unwrap_or_default() != holder.balance {
+        STAKED_BALANCES.save(storage, addr, &holder.balance, height)?;
+    }
+    Ok(())
+}
+
+pub fn remove_holder(storage: &mut dyn Storage, height: u64, addr: &Addr) -> StdResult<()> {
+    HOLDERS.remove(storage, addr);
+    STAKED_BALANCES.remove(storage, addr, height)
+}
+
+// save the state and snapshot the total staked at the height if it changed
+pub fn save_state(storage: &mut dyn Storage, height: u64, state: &State) -> StdResult<()> {
+    STATE.save(storage, state)?;
+    if STAKED_TOTAL.may_load(storage)?.unwrap_or_default() != state.total_staked {
+        STAKED_TOTAL.save(storage, &state.total_staked, height)?;
+    }
+    Ok(())
+}
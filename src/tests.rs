@@ -1,34 +1,130 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
 
     use cosmwasm_std::testing::{
         mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
     };
     use cosmwasm_std::{
-        from_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal256, MessageInfo, StdError, Uint128,
+        from_binary, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal256, Deps, DepsMut, Env,
+        MessageInfo, Order, Response, StdError, Uint128, Uint256, WasmMsg,
     };
-    use cw_utils::PaymentError;
+    use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
+    use cw_controllers::{Claim, ClaimsResponse};
+    use cw_storage_plus::Map;
+    use cw_utils::{Duration, Expiration, PaymentError};
 
-    use crate::contract::{execute, instantiate, query};
+    use crate::contract;
+    use crate::contract::{instantiate, migrate, query};
+    use crate::migrations::v0_1;
     use crate::msg::{
-        ConfigResponse, ExecuteMsg, HolderResponse, InstantiateMsg, QueryMsg, StateResponse,
+        AccruedReward, AccruedRewardsResponse, Asset, AssetSolvency, ConfigResponse, ContractCall,
+        ExecuteMsg, HolderResponse, HoldersResponse, InfoResponse, InstantiateMsg, MigrateMsg,
+        Operator, OperatorsResponse, QueryMsg, ReceiveMsg, RewardDenomsResponse,
+        RewardRecipientResponse, SolvencyResponse, StakedBalanceAtHeightResponse, StateResponse,
+        TotalPowerAtHeightResponse, TotalStakedAtHeightResponse, VotingPowerAtHeightResponse,
     };
-    use crate::{ContractError, state};
+    use crate::state::{
+        HolderReward, Operation, RewardState, HOLDERS, INDEX_SCALE, STAKED_BALANCES, STAKED_TOTAL,
+        STATE,
+    };
+    use crate::ContractError;
+    use proptest::collection::vec;
+    use proptest::option;
+    use proptest::prelude::*;
 
     fn default_init() -> InstantiateMsg {
         InstantiateMsg {
-            staked_token_denom: "staked".to_string(),
-            reward_denom: "rewards".to_string(),
+            staked_token_denom: Denom::Native("staked".to_string()),
+            reward_denoms: vec![Denom::Native("rewards".to_string())],
             admin: None,
+            unbonding_period: None,
+            reward_duration: None,
+            pauser: None,
+            allow_compounding: false,
+            transfers_enabled: false,
+        }
+    }
+
+    // same namespace as CLAIMS, whose map can't be iterated from outside
+    const CLAIMS_MAP: Map<&Addr, Vec<Claim>> = Map::new("claims");
+
+    // execute and check the contract invariants after every successful call
+    fn execute(
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: ExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        let res = contract::execute(deps.branch(), env, info, msg)?;
+        assert_invariants(deps.as_ref());
+        Ok(res)
+    }
+
+    // holder balances and claims add up to the state totals and settled rewards never exceed
+    // what is owed
+    fn assert_invariants(deps: Deps) {
+        let state = STATE.load(deps.storage).unwrap();
+        let holders = HOLDERS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.unwrap().1)
+            .collect::<Vec<_>>();
+
+        let total_balance: Uint128 = holders.iter().map(|holder| holder.balance).sum();
+        assert_eq!(total_balance, state.total_staked);
+        assert_eq!(
+            STAKED_TOTAL
+                .may_load(deps.storage)
+                .unwrap()
+                .unwrap_or_default(),
+            state.total_staked
+        );
+
+        let snapshotted: Uint128 = STAKED_BALANCES
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.unwrap().1)
+            .sum();
+        assert_eq!(snapshotted, state.total_staked);
+        for item in HOLDERS.range(deps.storage, None, None, Order::Ascending) {
+            let (addr, holder) = item.unwrap();
+            assert_eq!(
+                STAKED_BALANCES
+                    .may_load(deps.storage, &addr)
+                    .unwrap()
+                    .unwrap_or_default(),
+                holder.balance
+            );
+        }
+
+        let total_unbonding: Uint128 = CLAIMS_MAP
+            .range(deps.storage, None, None, Order::Ascending)
+            .flat_map(|item| item.unwrap().1)
+            .map(|claim| claim.amount)
+            .sum();
+        assert_eq!(total_unbonding, state.total_unbonding);
+
+        for reward in state.rewards.iter() {
+            let pending: Uint128 = holders
+                .iter()
+                .flat_map(|holder| holder.rewards.iter())
+                .filter(|holder_reward| holder_reward.denom == reward.denom)
+                .map(|holder_reward| holder_reward.pending_rewards)
+                .sum();
+            assert!(pending <= reward.total_rewards - reward.rewards_claimed);
         }
     }
 
+    // rewards per staked token scaled like the contract indexes
+    fn index(rewards: u128, staked: u128) -> Uint256 {
+        Uint256::from(rewards) * Uint256::from(INDEX_SCALE) / Uint256::from(staked)
+    }
+
     #[test]
     fn proper_init() {
         // Instantiate the contract with same denom
         let mut deps = mock_dependencies();
         let mut init_msg = default_init();
-        init_msg.staked_token_denom = "rewards".to_string();
+        init_msg.staked_token_denom = Denom::Native("rewards".to_string());
         let env = mock_env();
         let info = MessageInfo {
             sender: Addr::unchecked("creator"),
@@ -46,7 +142,7 @@ mod tests {
         };
 
         //instantiate without admin
-        let res = instantiate(deps.as_mut(), env.clone(), info, init_msg).unwrap();
+        let _res = instantiate(deps.as_mut(), env.clone(), info, init_msg).unwrap();
 
         let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
         let state: StateResponse = from_binary(&res).unwrap();
@@ -54,10 +150,10 @@ mod tests {
         assert_eq!(
             state,
             StateResponse {
-                global_index: Decimal256::zero(),
                 total_staked: Uint128::zero(),
-                total_rewards: Uint128::zero(),
-                rewards_claimed: Uint128::zero(),
+                total_unbonding: Uint128::zero(),
+                rewards: vec![RewardState::new(Denom::Native("rewards".to_string()))],
+                emergency: false,
             }
         );
         //query config
@@ -67,16 +163,28 @@ mod tests {
         assert_eq!(
             config_response,
             ConfigResponse {
-                staked_token_denom: "staked".to_string(),
-                reward_denom: "rewards".to_string(),
-                admin: "creator".to_string(),
+                staked_token_denom: Denom::Native("staked".to_string()),
+                admin: Some("creator".to_string()),
+                pending_admin: None,
+                pending_admin_expiry: None,
+                unbonding_period: None,
+                reward_duration: None,
+                pauser: None,
+                paused: vec![],
+                allow_compounding: false,
+                transfers_enabled: false,
             }
         );
         //instantiate with admin
         let init_msg = InstantiateMsg {
-            staked_token_denom: "staked".to_string(),
-            reward_denom: "rewards".to_string(),
+            staked_token_denom: Denom::Native("staked".to_string()),
+            reward_denoms: vec![Denom::Native("rewards".to_string())],
             admin: Some(Addr::unchecked("admin").to_string()),
+            unbonding_period: None,
+            reward_duration: None,
+            pauser: None,
+            allow_compounding: false,
+            transfers_enabled: false,
         };
         let info = MessageInfo {
             sender: Addr::unchecked("creator"),
@@ -88,7 +196,7 @@ mod tests {
         let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
         let config_response: ConfigResponse = from_binary(&res).unwrap();
         //admin is set to admin
-        assert_eq!(config_response.admin, "admin".to_string(),);
+        assert_eq!(config_response.admin, Some("admin".to_string()));
     }
 
     #[test]
@@ -102,43 +210,43 @@ mod tests {
 
         //bond with no fund
         let info = mock_info("staker1", &[]);
-        let msg = ExecuteMsg::BondStake {};
+        let msg = ExecuteMsg::BondStake { recipient: None };
         let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
         assert_eq!(res, PaymentError::NoFunds {}.into());
 
         //bond with wrong denom
         let info = mock_info(
             "random",
-            &vec![Coin {
+            &[Coin {
                 denom: "wrong".to_string(),
                 amount: Uint128::new(100),
             }],
         );
-        let msg = ExecuteMsg::BondStake {};
+        let msg = ExecuteMsg::BondStake { recipient: None };
         let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
         assert_eq!(res, PaymentError::MissingDenom("staked".to_string()).into());
 
         //bond with 0 amount
         let info = mock_info(
             "random",
-            &vec![Coin {
+            &[Coin {
                 denom: "staked".to_string(),
                 amount: Uint128::zero(),
             }],
         );
-        let msg = ExecuteMsg::BondStake {};
+        let msg = ExecuteMsg::BondStake { recipient: None };
         let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(res, ContractError::Payment(PaymentError::NoFunds {}.into()));
+        assert_eq!(res, ContractError::Payment(PaymentError::NoFunds {}));
 
         //first bond
         let info = mock_info(
             "staker1",
-            &vec![Coin {
+            &[Coin {
                 denom: "staked".to_string(),
                 amount: Uint128::new(100),
             }],
         );
-        let msg = ExecuteMsg::BondStake {};
+        let msg = ExecuteMsg::BondStake { recipient: None };
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         //query holder
@@ -156,9 +264,15 @@ mod tests {
             HolderResponse {
                 address: "staker1".to_string(),
                 balance: Uint128::new(100),
-                index: Decimal256::zero(),
-                pending_rewards: Uint128::zero(),
-                dec_rewards: Decimal256::zero(),
+                rewards: vec![HolderReward::new(
+                    Denom::Native("rewards".to_string()),
+                    Uint256::zero()
+                )],
+                settled_rewards: vec![Asset {
+                    denom: Denom::Native("rewards".to_string()),
+                    amount: Uint128::zero(),
+                }],
+                auto_compound: false,
             }
         );
 
@@ -182,12 +296,12 @@ mod tests {
         //second bond
         let info = mock_info(
             "staker2",
-            &vec![Coin {
+            &[Coin {
                 denom: "staked".to_string(),
                 amount: Uint128::new(100),
             }],
         );
-        let msg = ExecuteMsg::BondStake {};
+        let msg = ExecuteMsg::BondStake { recipient: None };
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         // update staker2 rewards
@@ -208,22 +322,19 @@ mod tests {
         let holder_response: HolderResponse = from_binary(&res).unwrap();
 
         //check if index is correct
-        assert_eq!(
-            holder_response.index,
-            Decimal256::from_ratio(Uint128::new(1000000), Uint128::new(100))
-        );
+        assert_eq!(holder_response.rewards[0].index, index(1000000, 100));
         // check if pending rewards is correct
-        assert_eq!(holder_response.pending_rewards, Uint128::new(0));
+        assert_eq!(holder_response.rewards[0].pending_rewards, Uint128::new(0));
 
         // bond again
         let info = mock_info(
             "staker2",
-            &vec![Coin {
+            &[Coin {
                 denom: "staked".to_string(),
                 amount: Uint128::new(100),
             }],
         );
-        let msg = ExecuteMsg::BondStake {};
+        let msg = ExecuteMsg::BondStake { recipient: None };
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
         //query staker2
@@ -238,10 +349,7 @@ mod tests {
         let holder_response: HolderResponse = from_binary(&res).unwrap();
 
         //check if index is correct
-        assert_eq!(
-            holder_response.index,
-            Decimal256::from_ratio(Uint128::new(1000000), Uint128::new(100))
-        );
+        assert_eq!(holder_response.rewards[0].index, index(1000000, 100));
         // check if amount is correct
         assert_eq!(holder_response.balance, Uint128::new(200));
 
@@ -281,13 +389,13 @@ mod tests {
         let holder_response: HolderResponse = from_binary(&res).unwrap();
 
         //check if index is correct
-        assert_eq!(
-            holder_response.index,
-            Decimal256::from_ratio(Uint128::new(4000000), Uint128::new(300))
-        );
+        assert_eq!(holder_response.rewards[0].index, index(4000000, 300));
 
         // check if pending rewards is correct
-        assert_eq!(holder_response.pending_rewards, Uint128::new(1333333));
+        assert_eq!(
+            holder_response.rewards[0].pending_rewards,
+            Uint128::new(1333333)
+        );
 
         // query staker2
         let res = query(
@@ -300,13 +408,191 @@ mod tests {
         let holder_response: HolderResponse = from_binary(&res.unwrap()).unwrap();
 
         //check if index is correct
+        assert_eq!(holder_response.rewards[0].index, index(4000000, 300));
+
+        // check if pending rewards is correct
         assert_eq!(
-            holder_response.index,
-            Decimal256::from_ratio(Uint128::new(4000000), Uint128::new(300))
+            holder_response.rewards[0].pending_rewards,
+            Uint128::new(666666)
         );
+    }
 
-        // check if pending rewards is correct
-        assert_eq!(holder_response.pending_rewards, Uint128::new(666666));
+    #[test]
+    pub fn test_bond_cw20() {
+        let mut deps = mock_dependencies();
+        let mut init_msg = default_init();
+        init_msg.staked_token_denom = Denom::Cw20(Addr::unchecked("share_token"));
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // native bond is not accepted
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidToken {});
+
+        // bond with wrong cw20 token
+        let info = mock_info("wrong_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "staker1".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::BondStake { recipient: None }).unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidToken {});
+
+        // bond with 0 amount
+        let info = mock_info("share_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "staker1".to_string(),
+            amount: Uint128::zero(),
+            msg: to_binary(&ReceiveMsg::BondStake { recipient: None }).unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::AmountRequired {});
+
+        // bond staker1
+        let info = mock_info("share_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "staker1".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::BondStake { recipient: None }).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // query holder
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.balance, Uint128::new(100));
+
+        // withdraw sends the stake back as a cw20 transfer
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "share_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "staker1".to_string(),
+                    amount: Uint128::new(100),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // check state for total staked
+        let res = query(deps.as_ref(), env, QueryMsg::State {}).unwrap();
+        let state: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state.total_staked, Uint128::zero());
+    }
+
+    #[test]
+    pub fn test_bond_for_recipient() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        // the multisig bonds for a member
+        let info = mock_info(
+            "multisig",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake {
+            recipient: Some("member".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(res.attributes[1].value, "member");
+        assert_eq!(res.attributes[3].value, "multisig");
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "member".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.balance, Uint128::new(100));
+
+        // the funder gets no position
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "multisig".to_string(),
+            },
+        );
+        assert!(res.is_err());
+
+        // a vesting contract bonds cw20 stake for a beneficiary
+        let mut deps = mock_dependencies();
+        let mut init_msg = default_init();
+        init_msg.staked_token_denom = Denom::Cw20(Addr::unchecked("share_token"));
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        let info = mock_info("share_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "vesting".to_string(),
+            amount: Uint128::new(50),
+            msg: to_binary(&ReceiveMsg::BondStake {
+                recipient: Some("beneficiary".to_string()),
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(res.attributes[1].value, "beneficiary");
+        assert_eq!(res.attributes[3].value, "vesting");
+
+        let res = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::Holder {
+                address: "beneficiary".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.balance, Uint128::new(50));
     }
 
     #[test]
@@ -332,14 +618,22 @@ mod tests {
             }],
         );
         let msg = ExecuteMsg::UpdateReward {};
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(res, ContractError::NoBond {});
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // rewards are kept undistributed until someone bonds
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].global_index, Uint256::zero());
+        assert_eq!(
+            state_response.rewards[0].undistributed_rewards,
+            Uint128::new(1000000)
+        );
 
         // update reward with multiple denom
 
         let info = mock_info(
             "creator",
-            &vec![
+            &[
                 Coin {
                     denom: "rewards".to_string(),
                     amount: Uint128::new(1000000),
@@ -352,7 +646,7 @@ mod tests {
         );
         let msg = ExecuteMsg::UpdateReward {};
         let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(res, ContractError::Payment(PaymentError::MultipleDenoms {}));
+        assert_eq!(res, ContractError::NotRewardDenom("rewards2".to_string()));
 
         // update reward with wrong denom
         let info = mock_info(
@@ -364,13 +658,10 @@ mod tests {
         );
         let msg = ExecuteMsg::UpdateReward {};
         let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(
-            res,
-            ContractError::Payment(PaymentError::MissingDenom("rewards".to_string()))
-        );
+        assert_eq!(res, ContractError::NotRewardDenom("wrong".to_string()));
 
         // update reward with 0 amount
-        let info= mock_info(
+        let info = mock_info(
             "creator",
             &[Coin {
                 denom: "rewards".to_string(),
@@ -384,14 +675,23 @@ mod tests {
         // bond staker1
         let info = mock_info(
             "staker1",
-            &vec![Coin {
+            &[Coin {
                 denom: "staked".to_string(),
                 amount: Uint128::new(100),
             }],
         );
-        let msg = ExecuteMsg::BondStake {};
+        let msg = ExecuteMsg::BondStake { recipient: None };
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
+        // first bond distributes the undistributed rewards
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].global_index, index(1000000, 100));
+        assert_eq!(
+            state_response.rewards[0].undistributed_rewards,
+            Uint128::zero()
+        );
+
         // update reward
         let info = mock_info(
             "creator",
@@ -408,114 +708,49 @@ mod tests {
         let state_response: StateResponse = from_binary(&res).unwrap();
 
         // check if reward index is correct
-        assert_eq!(
-            state_response.global_index,
-            Decimal256::from_ratio(Uint128::new(1000000), Uint128::new(100))
-        );
+        assert_eq!(state_response.rewards[0].global_index, index(2000000, 100));
 
         // check if reward pool is correct
-        assert_eq!(state_response.total_rewards, Uint128::new(1000000));
-    }
-
-    #[test]
-    pub fn test_update_holder_rewards() {
-        // instantiate contract
-        let mut deps = mock_dependencies_with_balance(&[]);
-        let init_msg = default_init();
-        let env = mock_env();
-        instantiate(
-            deps.as_mut(),
-            env.clone(),
-            mock_info("creator", &[]),
-            init_msg,
-        )
-        .unwrap();
-
-        // update staker 1 rewards without bonding
-        let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::UpdateHolderReward {
-            address: Some("staker1".to_string()),
-        };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
         assert_eq!(
-            res,
-            ContractError::Std(
-                (StdError::NotFound {
-                    kind: ("cw_share::state::Holder").to_string()
-                })
-            )
-        );
-        // bond staker1
-        let info = mock_info(
-            "staker1",
-            &vec![Coin {
-                denom: "staked".to_string(),
-                amount: Uint128::new(100),
-            }],
+            state_response.rewards[0].total_rewards,
+            Uint128::new(2000000)
         );
-        let msg = ExecuteMsg::BondStake {};
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // query staker 1
+        // staker1 receives both deposits
         let res = query(
             deps.as_ref(),
             env.clone(),
-            QueryMsg::Holder {
+            QueryMsg::AccruedRewards {
                 address: "staker1".to_string(),
             },
         )
         .unwrap();
+        let accrued: AccruedRewardsResponse = from_binary(&res).unwrap();
+        assert_eq!(accrued.rewards[0].claimable, Uint128::new(2000000));
+        assert_eq!(accrued.rewards[0].settled, Uint128::zero());
 
-        let holder_response: HolderResponse = from_binary(&res).unwrap();
-
-        // check if holder data is correct
-        assert_eq!(holder_response.pending_rewards, Uint128::new(0));
-        assert_eq!(holder_response.index, Decimal256::zero());
-        assert_eq!(holder_response.balance, Uint128::new(100));
-
-        // update reward
-        let info = mock_info(
-            "creator",
-            &[Coin {
-                denom: "rewards".to_string(),
-                amount: Uint128::new(1000000),
-            }],
-        );
-        let msg = ExecuteMsg::UpdateReward {};
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-
-        // update staker 1 rewards
+        // updating the holder settles the same amount
         let info = mock_info("staker1", &[]);
         let msg = ExecuteMsg::UpdateHolderReward { address: None };
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-
-        // query staker 1
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
         let res = query(
             deps.as_ref(),
-            env.clone(),
-            QueryMsg::Holder {
+            env,
+            QueryMsg::AccruedRewards {
                 address: "staker1".to_string(),
             },
         )
         .unwrap();
-
-        let holder_response: HolderResponse = from_binary(&res).unwrap();
-
-        // check if pending rewards is correct
-        assert_eq!(holder_response.pending_rewards, Uint128::new(1000000));
-
-        // check if reward index is correct
-        assert_eq!(
-            holder_response.index,
-            Decimal256::from_ratio(Uint128::new(1000000), Uint128::new(100))
-        );
+        let accrued: AccruedRewardsResponse = from_binary(&res).unwrap();
+        assert_eq!(accrued.rewards[0].claimable, Uint128::new(2000000));
+        assert_eq!(accrued.rewards[0].settled, Uint128::new(2000000));
     }
 
     #[test]
-    pub fn test_receive_rewards() {
-        // instantiate contract
-        let mut deps = mock_dependencies_with_balance(&[]);
-        let init_msg = default_init();
+    pub fn test_update_reward_cw20() {
+        let mut deps = mock_dependencies();
+        let mut init_msg = default_init();
+        init_msg.reward_denoms = vec![Denom::Cw20(Addr::unchecked("reward_token"))];
         let env = mock_env();
         instantiate(
             deps.as_mut(),
@@ -525,101 +760,2916 @@ mod tests {
         )
         .unwrap();
 
-        // try to receive rewards without bonding
-        let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::ReceiveReward {};
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(
-            res,
-            ContractError::Std(
-                (StdError::NotFound {
-                    kind: ("cw_share::state::Holder").to_string()
-                })
-            )
-        );
-
         // bond staker1
         let info = mock_info(
             "staker1",
-            &vec![Coin {
+            &[Coin {
                 denom: "staked".to_string(),
                 amount: Uint128::new(100),
             }],
         );
-        let msg = ExecuteMsg::BondStake {};
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-
-        // try to receive rewards without any reward
-        let info = mock_info("staker1", &[]);
-        let msg = ExecuteMsg::ReceiveReward {};
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(res, ContractError::NoRewards {});
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-        // update reward
+        // native rewards are not accepted
         let info = mock_info(
             "creator",
             &[Coin {
                 denom: "rewards".to_string(),
-                amount: Uint128::new(1000000),
+                amount: Uint128::new(100),
             }],
         );
         let msg = ExecuteMsg::UpdateReward {};
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-
-        // receive rewards
-        let info = mock_info("staker1", &[]);
-        let msg = ExecuteMsg::ReceiveReward {};
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NotRewardDenom("rewards".to_string()));
 
+        // update reward with wrong cw20 token
+        let info = mock_info("wrong_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "creator".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::UpdateRewardIndex {}).unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
         assert_eq!(
-            res.messages[0].msg,
-            CosmosMsg::Bank(BankMsg::Send {
-                to_address: "staker1".to_string(),
-                amount: vec![Coin {
+            res,
+            ContractError::NotRewardDenom("wrong_token".to_string())
+        );
+
+        // reward token can not be bonded
+        let info = mock_info("reward_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "creator".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::BondStake { recipient: None }).unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidToken {});
+
+        // update reward
+        let info = mock_info("reward_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "creator".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::UpdateRewardIndex {}).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // query state
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state.rewards[0].global_index, index(1, 1));
+        assert_eq!(state.rewards[0].total_rewards, Uint128::new(100));
+
+        // receive rewards as a cw20 transfer
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "reward_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "staker1".to_string(),
+                    amount: Uint128::new(100),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_multiple_reward_denoms() {
+        // instantiate with duplicate reward denoms
+        let mut deps = mock_dependencies();
+        let mut init_msg = default_init();
+        init_msg.reward_denoms = vec![
+            Denom::Native("rewards".to_string()),
+            Denom::Native("rewards".to_string()),
+        ];
+        let env = mock_env();
+        let res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::DuplicateDenom {});
+
+        let mut init_msg = default_init();
+        init_msg.reward_denoms = vec![
+            Denom::Native("rewards".to_string()),
+            Denom::Native("arewards".to_string()),
+            Denom::Cw20(Addr::unchecked("reward_token")),
+        ];
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // bond staker1 and staker2
+        for (staker, amount) in [("staker1", 100u128), ("staker2", 300u128)] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // update rewards of two native denoms in one call
+        let info = mock_info(
+            "creator",
+            &[
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(400),
+                },
+                Coin {
+                    denom: "arewards".to_string(),
+                    amount: Uint128::new(800),
+                },
+            ],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // update cw20 rewards
+        let info = mock_info("reward_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "creator".to_string(),
+            amount: Uint128::new(40),
+            msg: to_binary(&ReceiveMsg::UpdateRewardIndex {}).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // query state
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        let indexes: Vec<Uint256> = state_response
+            .rewards
+            .iter()
+            .map(|reward| reward.global_index)
+            .collect();
+        assert_eq!(indexes, vec![index(1, 1), index(2, 1), index(1, 10),]);
+
+        // update staker1 rewards
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward { address: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // query staker1 accrued rewards
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AccruedRewards {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let accrued: AccruedRewardsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            accrued.rewards,
+            vec![
+                AccruedReward {
+                    denom: Denom::Native("rewards".to_string()),
+                    claimable: Uint128::new(100),
+                    dec_rewards: Uint256::zero(),
+                    settled: Uint128::new(100),
+                },
+                AccruedReward {
+                    denom: Denom::Native("arewards".to_string()),
+                    claimable: Uint128::new(200),
+                    dec_rewards: Uint256::zero(),
+                    settled: Uint128::new(200),
+                },
+                AccruedReward {
+                    denom: Denom::Cw20(Addr::unchecked("reward_token")),
+                    claimable: Uint128::new(10),
+                    dec_rewards: Uint256::zero(),
+                    settled: Uint128::new(10),
+                },
+            ]
+        );
+
+        // receive every pending denom, native coins in one bank message
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker2".to_string(),
+                amount: vec![
+                    Coin {
+                        denom: "arewards".to_string(),
+                        amount: Uint128::new(600),
+                    },
+                    Coin {
+                        denom: "rewards".to_string(),
+                        amount: Uint128::new(300),
+                    },
+                ],
+            })
+        );
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "reward_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "staker2".to_string(),
+                    amount: Uint128::new(30),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // claimed rewards are tracked per denom
+        let res = query(deps.as_ref(), env, QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        let claimed: Vec<Uint128> = state_response
+            .rewards
+            .iter()
+            .map(|reward| reward.rewards_claimed)
+            .collect();
+        assert_eq!(
+            claimed,
+            vec![Uint128::new(300), Uint128::new(600), Uint128::new(30)]
+        );
+    }
+
+    #[test]
+    pub fn test_sync_rewards() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        // bond staker1
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // nothing to sync while the balance is owed to holders
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                },
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                },
+            ],
+        );
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::SyncRewards {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoRewards {});
+
+        // rewards sent with a plain bank transfer
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                },
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(150),
+                },
+            ],
+        );
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::SyncRewards {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(res.attributes[1].value, "50rewards");
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].global_index, index(150, 100));
+        assert_eq!(state_response.rewards[0].total_rewards, Uint128::new(150));
+
+        // synced rewards are not distributed twice
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::SyncRewards {};
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoRewards {});
+    }
+
+    #[test]
+    pub fn test_reward_denom_whitelist() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        // random can't add reward denom
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::AddRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // staked denom can't be a reward denom
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRewardDenom {
+            denom: Denom::Native("staked".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::SameDenom {});
+
+        // active denom can't be added twice
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRewardDenom {
+            denom: Denom::Native("rewards".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::DuplicateDenom {});
+
+        // admin adds reward denom
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::RewardDenoms {}).unwrap();
+        let denoms: RewardDenomsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            denoms.reward_denoms,
+            vec![
+                Denom::Native("rewards".to_string()),
+                Denom::Native("rewards2".to_string()),
+            ]
+        );
+
+        // update reward with new denom while nothing is staked
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards2".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // denom with undistributed rewards can't be removed
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::UndistributedRewards {});
+
+        // bond staker1, the parked rewards are distributed
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // staker1 claims rewards
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // random can't remove reward denom
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::RemoveRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // admin removes reward denom
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::RewardDenoms {}).unwrap();
+        let denoms: RewardDenomsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            denoms.reward_denoms,
+            vec![Denom::Native("rewards".to_string())]
+        );
+
+        // removed denom is not accepted anymore
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards2".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NotRewardDenom("rewards2".to_string()));
+
+        // removed denom can't be removed again
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NotRewardDenom("rewards2".to_string()));
+
+        // re-added denom continues from its last index
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env, QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[1].global_index, index(1, 1));
+        assert!(state_response.rewards[1].active);
+    }
+
+    #[test]
+    pub fn test_remove_reward_denom_with_fractions() {
+        let mut deps = mock_dependencies();
+        let mut init_msg = default_init();
+        init_msg.reward_denoms = vec![
+            Denom::Native("rewards".to_string()),
+            Denom::Native("rewards2".to_string()),
+        ];
+        init_msg.reward_duration = Some(100);
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        for staker in ["staker1", "staker2", "staker3"] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(1),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards2".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // denom can't be removed while it is streamed
+        env.block.time = env.block.time.plus_seconds(50);
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::UndistributedRewards {});
+
+        // 100 rewards don't split evenly over 3 tokens, the fractions don't block the removal
+        env.block.time = env.block.time.plus_seconds(50);
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RemoveRewardDenom {
+            denom: Denom::Native("rewards2".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // holders still claim the rewards of the removed denom
+        for staker in ["staker1", "staker2", "staker3"] {
+            let info = mock_info(staker, &[]);
+            let msg = ExecuteMsg::ReceiveReward {
+                recipient: None,
+                call: None,
+                owner: None,
+            };
+            let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+            assert_eq!(
+                res.messages[0].msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: staker.to_string(),
+                    amount: vec![Coin {
+                        denom: "rewards2".to_string(),
+                        amount: Uint128::new(33),
+                    }],
+                })
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_update_holder_rewards() {
+        // instantiate contract
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let init_msg = default_init();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // update staker 1 rewards without bonding
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward {
+            address: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::Std(StdError::NotFound {
+                kind: ("cw_share::state::Holder").to_string()
+            })
+        );
+        // bond staker1
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // query staker 1
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+
+        // check if holder data is correct
+        assert_eq!(holder_response.rewards[0].pending_rewards, Uint128::new(0));
+        assert_eq!(holder_response.rewards[0].index, Uint256::zero());
+        assert_eq!(holder_response.balance, Uint128::new(100));
+
+        // update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(1000000),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // update staker 1 rewards
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward { address: None };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // query staker 1
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+
+        // check if pending rewards is correct
+        assert_eq!(
+            holder_response.rewards[0].pending_rewards,
+            Uint128::new(1000000)
+        );
+
+        // check if reward index is correct
+        assert_eq!(holder_response.rewards[0].index, index(1000000, 100));
+    }
+
+    #[test]
+    pub fn test_query_accrued_rewards() {
+        let mut deps = mock_dependencies();
+        let mut init_msg = default_init();
+        init_msg.reward_duration = Some(100);
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // bond staker1 and staker2
+        for (staker, amount) in [("staker1", 100u128), ("staker2", 200u128)] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(1000),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // queries include what is streamed so far without any update
+        env.block.time = env.block.time.plus_seconds(10);
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AccruedRewards {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let accrued: AccruedRewardsResponse = from_binary(&res).unwrap();
+        assert_eq!(accrued.rewards[0].claimable, Uint128::new(33));
+        assert_eq!(accrued.rewards[0].settled, Uint128::zero());
+        assert!(accrued.rewards[0].dec_rewards > Uint256::zero());
+        assert!(accrued.rewards[0].dec_rewards < Uint256::from(INDEX_SCALE));
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let holders: HoldersResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            holders.holders[1].rewards[0].pending_rewards,
+            Uint128::new(66)
+        );
+        assert_eq!(
+            holders.holders[1].settled_rewards[0].amount,
+            Uint128::zero()
+        );
+
+        // settling matches the simulated amount
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward { address: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::Holder {
+                address: "staker2".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.rewards[0].pending_rewards, Uint128::new(66));
+        assert_eq!(holder_response.settled_rewards[0].amount, Uint128::new(66));
+    }
+
+    #[test]
+    pub fn test_receive_rewards() {
+        // instantiate contract
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let init_msg = default_init();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // try to receive rewards without bonding
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::Std(StdError::NotFound {
+                kind: ("cw_share::state::Holder").to_string()
+            })
+        );
+
+        // bond staker1
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // try to receive rewards without any reward
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(res, ContractError::NoRewards {});
+
+        // update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(1000000),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // receive rewards
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(1000000),
+                }],
+            })
+        );
+
+        // query staker 1
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        // check if pending rewards is correct
+        assert_eq!(holder_response.rewards[0].pending_rewards, Uint128::new(0));
+        // check if reward index is correct
+        assert_eq!(holder_response.rewards[0].index, index(1000000, 100));
+        // query state
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+
+        assert_eq!(
+            state_response.rewards[0].total_rewards,
+            Uint128::new(1000000)
+        );
+        assert_eq!(
+            state_response.rewards[0].rewards_claimed,
+            Uint128::new(1000000)
+        );
+
+        // try to receive rewards again
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(res, ContractError::NoRewards {});
+    }
+
+    #[test]
+    pub fn test_reward_recipient() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        let info = mock_info(
+            "treasury",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        fn reward(deps: DepsMut, amount: u128) {
+            let info = mock_info(
+                "creator",
+                &[Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            );
+            let msg = ExecuteMsg::UpdateReward {};
+            let _res = execute(deps, mock_env(), info, msg).unwrap();
+        }
+        fn sent_to(res: &Response, recipient: &str, amount: u128) {
+            assert_eq!(
+                res.messages[0].msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: recipient.to_string(),
+                    amount: vec![Coin {
+                        denom: "rewards".to_string(),
+                        amount: Uint128::new(amount),
+                    }],
+                })
+            );
+        }
+
+        // rewards go to the holder by default
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RewardRecipient {
+                address: "treasury".to_string(),
+            },
+        )
+        .unwrap();
+        let recipient: RewardRecipientResponse = from_binary(&res).unwrap();
+        assert_eq!(recipient.recipient, "treasury".to_string());
+
+        // claim to a one-off recipient
+        reward(deps.as_mut(), 10);
+        let info = mock_info("treasury", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: Some("grants".to_string()),
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        sent_to(&res, "grants", 10);
+
+        // claims and withdrawals honor the reward recipient
+        let info = mock_info("treasury", &[]);
+        let msg = ExecuteMsg::SetRewardRecipient {
+            address: Some("operations".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RewardRecipient {
+                address: "treasury".to_string(),
+            },
+        )
+        .unwrap();
+        let recipient: RewardRecipientResponse = from_binary(&res).unwrap();
+        assert_eq!(recipient.recipient, "operations".to_string());
+
+        reward(deps.as_mut(), 20);
+        let info = mock_info("treasury", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        sent_to(&res, "operations", 20);
+
+        reward(deps.as_mut(), 30);
+        let info = mock_info("treasury", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(50)),
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        sent_to(&res, "operations", 30);
+        // the stake goes back to the holder
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(50),
+                }],
+            })
+        );
+
+        // unset the reward recipient
+        let info = mock_info("treasury", &[]);
+        let msg = ExecuteMsg::SetRewardRecipient { address: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        reward(deps.as_mut(), 40);
+        let info = mock_info("treasury", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        sent_to(&res, "treasury", 40);
+    }
+
+    #[test]
+    pub fn test_receive_reward_call() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.reward_denoms = vec![
+            Denom::Native("rewards".to_string()),
+            Denom::Cw20(Addr::unchecked("reward_token")),
+        ];
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let info = mock_info("reward_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "creator".to_string(),
+            amount: Uint128::new(50),
+            msg: to_binary(&ReceiveMsg::UpdateRewardIndex {}).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let call = ContractCall {
+            contract_addr: "vault".to_string(),
+            msg: to_binary(&"deposit").unwrap(),
+        };
+
+        // a call replaces the recipient
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: Some("operations".to_string()),
+            call: Some(call.clone()),
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::RecipientAndCall {});
+
+        // the call can't target the pool, the staked token or a reward token
+        for target in ["cosmos2contract", "reward_token"] {
+            let info = mock_info("staker1", &[]);
+            let msg = ExecuteMsg::ReceiveReward {
+                recipient: None,
+                call: Some(ContractCall {
+                    contract_addr: target.to_string(),
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: "attacker".to_string(),
+                        amount: Uint128::new(100),
+                    })
+                    .unwrap(),
+                }),
+                owner: None,
+            };
+            let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+            assert_eq!(res, ContractError::InvalidCallTarget {});
+        }
+
+        // native rewards are attached as funds, cw20 rewards are sent with the call
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: Some(call.clone()),
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "vault".to_string(),
+                msg: call.msg.clone(),
+                funds: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            })
+        );
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "reward_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "vault".to_string(),
+                    amount: Uint128::new(50),
+                    msg: call.msg,
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_receive_reward_call_cw20_stake() {
+        // the stake token can't be the target of a claim call
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.staked_token_denom = Denom::Cw20(Addr::unchecked("stake_token"));
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        let info = mock_info("stake_token", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "staker1".to_string(),
+            amount: Uint128::new(1),
+            msg: to_binary(&ReceiveMsg::BondStake { recipient: None }).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(1),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: Some(ContractCall {
+                contract_addr: "stake_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "attacker".to_string(),
+                    amount: Uint128::new(1_000_000),
+                })
+                .unwrap(),
+            }),
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidCallTarget {});
+    }
+
+    #[test]
+    pub fn test_compound() {
+        // compounding is refused unless allowed
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AddRewardDenom {
+            denom: Denom::Native("staked".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::SameDenom {});
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::Compound { owner: None };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::CompoundingDisabled {});
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::SetAutoCompound { enabled: true };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::CompoundingDisabled {});
+
+        // the staked token is distributed as a reward
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.reward_denoms = vec![Denom::Native("staked".to_string())];
+        init_msg.allow_compounding = true;
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        for staker in ["staker1", "staker2"] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::SetAutoCompound { enabled: true };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // staker1 compounds manually
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::Compound { owner: None };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[2].value, "50");
+        assert_eq!(res.attributes[3].value, "150");
+
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::Compound { owner: None };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoRewards {});
+
+        // auto compounding waits while bonding is paused
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Pause {
+            operations: vec![Operation::Bond],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward {
+            address: Some("staker2".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker2".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.balance, Uint128::new(100));
+        assert_eq!(holder_response.rewards[0].pending_rewards, Uint128::new(50));
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Unpause {
+            operations: vec![Operation::Bond],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // staker2 rewards are compounded as of the current block
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker2".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.balance, Uint128::new(150));
+        assert_eq!(holder_response.rewards[0].pending_rewards, Uint128::zero());
+        assert!(holder_response.auto_compound);
+
+        // and on the next update
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward { address: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.total_staked, Uint128::new(300));
+        assert_eq!(state_response.rewards[0].rewards_claimed, Uint128::new(100));
+
+        // the compounded stake is owed as principal and can't be synced as a reward
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(300),
+            }],
+        );
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::SyncRewards {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoRewards {});
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Solvency {}).unwrap();
+        let solvency: SolvencyResponse = from_binary(&res).unwrap();
+        assert!(solvency.solvent);
+        assert_eq!(solvency.assets.len(), 1);
+        assert_eq!(solvency.assets[0].principal, Uint128::new(300));
+
+        // withdrawing everything includes the compounded rewards
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker2".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(150),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_compound_emergency() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.reward_denoms = vec![Denom::Native("staked".to_string())];
+        init_msg.allow_compounding = true;
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        for staker in ["staker1", "staker2"] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::SetAutoCompound { enabled: true };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AdminEmergencyWithdraw {
+            assets: vec![Asset {
+                denom: Denom::Native("staked".to_string()),
+                amount: Uint128::new(100),
+            }],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // the stake is frozen, nothing is compounded in emergency mode
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward {
+            address: Some("staker2".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::EmergencyMode {});
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::SetAutoCompound { enabled: true };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::EmergencyMode {});
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker2".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.balance, Uint128::new(100));
+        assert_eq!(holder_response.rewards[0].pending_rewards, Uint128::new(50));
+
+        let res = query(deps.as_ref(), env, QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.total_staked, Uint128::new(200));
+    }
+
+    #[test]
+    pub fn test_transfer_stake() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        for (staker, amount) in [("staker1", 100u128), ("staker2", 100u128)] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(200),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // transfers are disabled by default
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::TransferStake {
+            recipient: "staker2".to_string(),
+            amount: Uint128::new(50),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::TransfersDisabled {});
+
+        // only the admin can enable them
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::SetTransfersEnabled { enabled: true };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetTransfersEnabled { enabled: true };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::TransferStake {
+            recipient: "staker1".to_string(),
+            amount: Uint128::new(50),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::SelfTransfer {});
+
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::TransferStake {
+            recipient: "staker2".to_string(),
+            amount: Uint128::new(101),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::DecreaseAmountExceeds(Uint128::new(100)));
+
+        // staker1 moves half of its stake to staker2 and everything to staker3
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::TransferStake {
+            recipient: "staker2".to_string(),
+            amount: Uint128::new(50),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert!(res.messages.is_empty());
+
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::TransferStake {
+            recipient: "staker3".to_string(),
+            amount: Uint128::new(50),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // rewards accrued before the transfers stay with the previous balances
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(200),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        for (staker, balance, pending) in [
+            ("staker1", 0u128, 100u128),
+            ("staker2", 150, 250),
+            ("staker3", 50, 50),
+        ] {
+            let res = query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Holder {
+                    address: staker.to_string(),
+                },
+            )
+            .unwrap();
+            let holder_response: HolderResponse = from_binary(&res).unwrap();
+            assert_eq!(holder_response.balance, Uint128::new(balance));
+            assert_eq!(
+                holder_response.rewards[0].pending_rewards,
+                Uint128::new(pending)
+            );
+        }
+
+        let res = query(deps.as_ref(), env, QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.total_staked, Uint128::new(200));
+    }
+
+    #[test]
+    pub fn test_operators() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // expired approvals are refused
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ApproveOperator {
+            operator: "bot".to_string(),
+            expires: Some(Expiration::AtHeight(env.block.height)),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Expired {});
+
+        let expires = Expiration::AtHeight(env.block.height + 10);
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ApproveOperator {
+            operator: "bot".to_string(),
+            expires: Some(expires),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Operators {
+                owner: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let operators: OperatorsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            operators.operators,
+            vec![Operator {
+                operator: "bot".to_string(),
+                expires,
+            }]
+        );
+
+        // other addresses can't act for the owner
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // the operator can't redirect the rewards
+        let info = mock_info("bot", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: Some("bot".to_string()),
+            call: None,
+            owner: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // the operator claims for the owner
+        let info = mock_info("bot", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            })
+        );
+
+        // and withdraws stake back to the owner
+        let info = mock_info("bot", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(40)),
+            owner: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(40),
+                }],
+            })
+        );
+
+        // the approval ends with the expiry
+        let mut expired_env = env.clone();
+        expired_env.block.height += 10;
+        let info = mock_info("bot", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: None,
+            owner: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), expired_env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+        let res = query(
+            deps.as_ref(),
+            expired_env,
+            QueryMsg::Operators {
+                owner: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let operators: OperatorsResponse = from_binary(&res).unwrap();
+        assert!(operators.operators.is_empty());
+
+        // or when it is revoked
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::RevokeOperator {
+            operator: "bot".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let info = mock_info("bot", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: None,
+            owner: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    pub fn test_staked_balance_at_height() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let start = env.block.height;
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        for (staker, amount) in [("staker1", 100u128), ("staker2", 50u128)] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // staker1 withdraws everything a few blocks later
+        env.block.height += 5;
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: None,
+            owner: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let balance_at = |deps: Deps, address: &str, height: Option<u64>| {
+            let res = query(
+                deps,
+                env.clone(),
+                QueryMsg::StakedBalanceAtHeight {
+                    address: address.to_string(),
+                    height,
+                },
+            )
+            .unwrap();
+            from_binary::<StakedBalanceAtHeightResponse>(&res).unwrap()
+        };
+        let total_at = |deps: Deps, height: Option<u64>| {
+            let res = query(deps, env.clone(), QueryMsg::TotalStakedAtHeight { height }).unwrap();
+            from_binary::<TotalStakedAtHeightResponse>(&res).unwrap()
+        };
+
+        // balances are recorded at the start of the block, before the bond
+        assert_eq!(
+            balance_at(deps.as_ref(), "staker1", Some(start)).balance,
+            Uint128::zero()
+        );
+        assert_eq!(total_at(deps.as_ref(), Some(start)).total, Uint128::zero());
+
+        assert_eq!(
+            balance_at(deps.as_ref(), "staker1", Some(start + 1)).balance,
+            Uint128::new(100)
+        );
+        assert_eq!(
+            balance_at(deps.as_ref(), "staker1", Some(start + 5)).balance,
+            Uint128::new(100)
+        );
+        assert_eq!(
+            total_at(deps.as_ref(), Some(start + 5)).total,
+            Uint128::new(150)
+        );
+
+        // the current block is used by default
+        assert_eq!(
+            balance_at(deps.as_ref(), "staker1", None),
+            StakedBalanceAtHeightResponse {
+                balance: Uint128::new(100),
+                height: start + 5,
+            }
+        );
+        assert_eq!(
+            balance_at(deps.as_ref(), "staker1", Some(start + 6)).balance,
+            Uint128::zero()
+        );
+        assert_eq!(
+            balance_at(deps.as_ref(), "staker2", Some(start + 6)).balance,
+            Uint128::new(50)
+        );
+        assert_eq!(
+            total_at(deps.as_ref(), Some(start + 6)),
+            TotalStakedAtHeightResponse {
+                total: Uint128::new(50),
+                height: start + 6,
+            }
+        );
+        assert_eq!(
+            balance_at(deps.as_ref(), "random", Some(start + 6)).balance,
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    pub fn test_voting_module() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let start = env.block.height;
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("dao", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Dao {}).unwrap();
+        assert_eq!(from_binary::<Addr>(&res).unwrap(), Addr::unchecked("dao"));
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Info {}).unwrap();
+        let info_response: InfoResponse = from_binary(&res).unwrap();
+        assert_eq!(info_response.info.contract, "crates.io:cw-share");
+        assert_eq!(info_response.info.version, env!("CARGO_PKG_VERSION"));
+
+        for (staker, amount) in [("staker1", 100u128), ("staker2", 300u128)] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // unbonding stake has no voting power
+        env.block.height += 1;
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(100)),
+            owner: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        env.block.height += 1;
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::VotingPowerAtHeight {
+                address: "staker2".to_string(),
+                height: Some(start + 1),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            from_binary::<VotingPowerAtHeightResponse>(&res).unwrap(),
+            VotingPowerAtHeightResponse {
+                power: Uint128::new(300),
+                height: start + 1,
+            }
+        );
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::VotingPowerAtHeight {
+                address: "staker2".to_string(),
+                height: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            from_binary::<VotingPowerAtHeightResponse>(&res).unwrap(),
+            VotingPowerAtHeightResponse {
+                power: Uint128::new(200),
+                height: start + 2,
+            }
+        );
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::TotalPowerAtHeight {
+                height: Some(start),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            from_binary::<TotalPowerAtHeightResponse>(&res).unwrap(),
+            TotalPowerAtHeightResponse {
+                power: Uint128::zero(),
+                height: start,
+            }
+        );
+        let res = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::TotalPowerAtHeight { height: None },
+        )
+        .unwrap();
+        assert_eq!(
+            from_binary::<TotalPowerAtHeightResponse>(&res).unwrap(),
+            TotalPowerAtHeightResponse {
+                power: Uint128::new(300),
+                height: start + 2,
+            }
+        );
+    }
+
+    #[test]
+    pub fn test_withdraw() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let init_msg = default_init();
+        let env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        //first bond
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+
+        //second bond
+        let info = mock_info(
+            "staker2",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(200),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+
+        //update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        //withdraw staker1's stake without cap
+        let _info: MessageInfo = mock_info("staker1", &[]);
+        let _msg = ExecuteMsg::WithdrawStake {
+            amount: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), _info.clone(), _msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(33),
+                }],
+            }),
+        );
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            }),
+        );
+
+        //check state for total staked
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state.total_staked, Uint128::new(200));
+
+        //withdraw staker2's stake with cap
+        let _info: MessageInfo = mock_info("staker2", &[]);
+        let _msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(100)),
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), _info.clone(), _msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker2".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(66),
+                }],
+            }),
+        );
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker2".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            })
+        );
+
+        //check state for total staked
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state.total_staked, Uint128::new(100));
+    }
+
+    #[test]
+    pub fn test_withdraw_with_unbonding_period() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.unbonding_period = Some(Duration::Time(100));
+        let env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // bond staker1
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // nothing to claim before withdrawing
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::Claim {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NothingToClaim {});
+
+        // withdraw sends rewards only and locks the stake
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(60)),
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            })
+        );
+
+        // stake is no longer counted
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state.total_staked, Uint128::new(40));
+
+        // query claims
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Claims {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let claims: ClaimsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            claims.claims,
+            vec![Claim::new(60, Duration::Time(100).after(&env.block))]
+        );
+
+        // claim before unbonding period is over
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::Claim {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NothingToClaim {});
+
+        // claim after unbonding period is over
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::Claim {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(60),
+                }],
+            })
+        );
+
+        // claims are cleared
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Claims {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let claims: ClaimsResponse = from_binary(&res).unwrap();
+        assert!(claims.claims.is_empty());
+    }
+
+    #[test]
+    pub fn test_streaming_rewards() {
+        let mut deps = mock_dependencies();
+        let mut init_msg = default_init();
+        init_msg.reward_duration = Some(0);
+        let mut env = mock_env();
+        let res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::InvalidRewardDuration {});
+
+        // the end of a stream must not overflow
+        init_msg.reward_duration = Some(u64::MAX);
+        let res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::InvalidRewardDuration {});
+
+        init_msg.reward_duration = Some(100);
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+        let start = env.block.time.seconds();
+
+        // bond staker1
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // update reward starts the stream
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(1000),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].reward_rate, index(10, 1));
+        assert_eq!(state_response.rewards[0].period_finish, start + 100);
+        assert_eq!(state_response.rewards[0].global_index, Uint256::zero());
+
+        // half of the stream is distributed after half of the duration
+        env.block.time = env.block.time.plus_seconds(50);
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward { address: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AccruedRewards {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let accrued: AccruedRewardsResponse = from_binary(&res).unwrap();
+        assert_eq!(accrued.rewards[0].claimable, Uint128::new(500));
+
+        // bond staker2 in the middle of the stream
+        let info = mock_info(
+            "staker2",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // new deposit is streamed together with the remaining rewards
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(500),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].reward_rate, index(10, 1));
+        assert_eq!(state_response.rewards[0].period_finish, start + 150);
+
+        // a deposit smaller than the rewards left tops up the stream without delaying its end
+        env.block.time = env.block.time.plus_seconds(50);
+        let info = mock_info(
+            "random",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(10),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].reward_rate, index(51, 5));
+        assert_eq!(state_response.rewards[0].period_finish, start + 150);
+
+        // nothing more is streamed after the period is finished
+        env.block.time = env.block.time.plus_seconds(200);
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(1005),
+                }],
+            })
+        );
+
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker2".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(505),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_admin_withdraw_surplus() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        // bond staker1
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                },
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(150),
+                },
+                Coin {
+                    denom: "other".to_string(),
+                    amount: Uint128::new(10),
+                },
+            ],
+        );
+
+        // random can't withdraw surplus
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::AdminWithdrawSurplus {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // stake and owed rewards stay in the contract
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AdminWithdrawSurplus {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".to_string(),
+                amount: vec![
+                    Coin {
+                        denom: "other".to_string(),
+                        amount: Uint128::new(10),
+                    },
+                    Coin {
+                        denom: "rewards".to_string(),
+                        amount: Uint128::new(50),
+                    },
+                ],
+            })
+        );
+
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                },
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                },
+            ],
+        );
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AdminWithdrawSurplus {};
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoSurplus {});
+    }
+
+    #[test]
+    pub fn test_dust() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        // three holders of 1 token share 2 rewards, each accrues 2/3
+        for staker in ["staker1", "staker2", "staker3"] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(1),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(2),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // nothing to redistribute before a holder exits
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::RedistributeDust {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoDust {});
+
+        // exiting holders leave their fractions behind
+        for staker in ["staker1", "staker2"] {
+            let info = mock_info(staker, &[]);
+            let msg = ExecuteMsg::WithdrawStake {
+                amount: None,
+                owner: None,
+            };
+            let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+            assert_eq!(res.attributes[3].value, "");
+        }
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            state_response.rewards[0].dust,
+            index(2, 3) * Uint256::from(2u128)
+        );
+
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(1),
+                },
+                Coin {
                     denom: "rewards".to_string(),
-                    amount: Uint128::new(1000000),
+                    amount: Uint128::new(3),
+                },
+            ],
+        );
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Solvency {}).unwrap();
+        let solvency: SolvencyResponse = from_binary(&res).unwrap();
+        assert!(solvency.solvent);
+        assert_eq!(
+            solvency.assets,
+            vec![
+                AssetSolvency {
+                    denom: Denom::Native("staked".to_string()),
+                    balance: Uint128::new(1),
+                    principal: Uint128::new(1),
+                    owed_rewards: Uint128::zero(),
+                    dust: Uint128::zero(),
+                    surplus: Uint128::zero(),
+                    deficit: Uint128::zero(),
+                },
+                AssetSolvency {
+                    denom: Denom::Native("rewards".to_string()),
+                    balance: Uint128::new(3),
+                    principal: Uint128::zero(),
+                    owed_rewards: Uint128::new(1),
+                    dust: Uint128::new(1),
+                    surplus: Uint128::new(1),
+                    deficit: Uint128::zero(),
+                },
+            ]
+        );
+
+        // the remaining holder receives the dust, the index remainder is paid out with it
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::RedistributeDust {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(res.attributes[1].key, "scaled_dust");
+        assert_eq!(
+            res.attributes[1].value,
+            format!("{}rewards", index(4, 3) + Uint256::from(1u128))
+        );
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].dust, Uint256::zero());
+
+        let info = mock_info("staker3", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker3".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(2),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_solvency() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.unbonding_period = Some(Duration::Time(100));
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // bond 100 and unbond 40 of it
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(40)),
+            owner: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // the rewards were paid out with the withdraw, part of the stake is missing
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(90),
+                },
+                Coin {
+                    denom: "other".to_string(),
+                    amount: Uint128::new(10),
+                },
+            ],
+        );
+        let res = query(deps.as_ref(), env, QueryMsg::Solvency {}).unwrap();
+        let solvency: SolvencyResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            solvency,
+            SolvencyResponse {
+                solvent: false,
+                assets: vec![
+                    AssetSolvency {
+                        denom: Denom::Native("staked".to_string()),
+                        balance: Uint128::new(90),
+                        principal: Uint128::new(100),
+                        owed_rewards: Uint128::zero(),
+                        dust: Uint128::zero(),
+                        surplus: Uint128::zero(),
+                        deficit: Uint128::new(10),
+                    },
+                    AssetSolvency {
+                        denom: Denom::Native("rewards".to_string()),
+                        balance: Uint128::zero(),
+                        principal: Uint128::zero(),
+                        owed_rewards: Uint128::zero(),
+                        dust: Uint128::zero(),
+                        surplus: Uint128::zero(),
+                        deficit: Uint128::zero(),
+                    },
+                    AssetSolvency {
+                        denom: Denom::Native("other".to_string()),
+                        balance: Uint128::new(10),
+                        principal: Uint128::zero(),
+                        owed_rewards: Uint128::zero(),
+                        dust: Uint128::zero(),
+                        surplus: Uint128::new(10),
+                        deficit: Uint128::zero(),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    pub fn test_emergency_exit() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.unbonding_period = Some(Duration::Time(100));
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        // bond staker1 and staker2
+        for staker in ["staker1", "staker2"] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // update reward
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(200),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // staker2 withdraws half of the stake into unbonding
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(50)),
+            owner: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // exit is only possible in emergency mode
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::EmergencyExit {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NotEmergencyMode {});
+
+        // random can't make an emergency withdraw
+        let assets = vec![Asset {
+            denom: Denom::Native("staked".to_string()),
+            amount: Uint128::new(100),
+        }];
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::AdminEmergencyWithdraw {
+            assets: assets.clone(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // admin drains half of the stake
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AdminEmergencyWithdraw { assets };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            })
+        );
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                },
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                },
+            ],
+        );
+
+        // bonding is frozen
+        let info = mock_info(
+            "staker3",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::EmergencyMode {});
+
+        // staker1 exits with half of the remaining stake and the rewards it is still owed,
+        // staker2 was paid its rewards on withdraw
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::EmergencyExit {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![
+                    Coin {
+                        denom: "rewards".to_string(),
+                        amount: Uint128::new(100),
+                    },
+                    Coin {
+                        denom: "staked".to_string(),
+                        amount: Uint128::new(50),
+                    },
+                ],
+            })
+        );
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(50),
+            }],
+        );
+
+        // staker2 exits with the rest of the stake, including the unbonding stake
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::EmergencyExit {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker2".to_string(),
+                amount: vec![Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(50),
                 }],
             })
         );
 
-        // query staker 1
         let res = query(
             deps.as_ref(),
             env.clone(),
-            QueryMsg::Holder {
-                address: "staker1".to_string(),
+            QueryMsg::Claims {
+                address: "staker2".to_string(),
             },
         )
         .unwrap();
+        let claims: ClaimsResponse = from_binary(&res).unwrap();
+        assert_eq!(claims.claims, vec![]);
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.total_staked, Uint128::zero());
+        assert_eq!(state_response.total_unbonding, Uint128::zero());
+        assert!(state_response.emergency);
+
+        // staker2 can't exit twice
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::EmergencyExit {};
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoBond {});
+    }
+
+    #[test]
+    pub fn test_emergency_exit_pending_rewards() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let mut init_msg = default_init();
+        init_msg.transfers_enabled = true;
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        for staker in ["staker1", "staker2"] {
+            let info = mock_info(
+                staker,
+                &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            );
+            let msg = ExecuteMsg::BondStake { recipient: None };
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(200),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // staker1 moves its whole stake and keeps its pending rewards
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::TransferStake {
+            recipient: "staker2".to_string(),
+            amount: Uint128::new(100),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AdminEmergencyWithdraw {
+            assets: vec![Asset {
+                denom: Denom::Native("staked".to_string()),
+                amount: Uint128::new(100),
+            }],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                },
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(200),
+                },
+            ],
+        );
+
+        // a holder without stake still exits with its rewards
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::EmergencyExit {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker1".to_string(),
+                amount: vec![Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                }],
+            })
+        );
+        deps.querier.update_balance(
+            env.contract.address.as_str(),
+            vec![
+                Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(100),
+                },
+                Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(100),
+                },
+            ],
+        );
 
-        let holder_response: HolderResponse = from_binary(&res).unwrap();
-        // check if pending rewards is correct
-        assert_eq!(holder_response.pending_rewards, Uint128::new(0));
-        // check if reward index is correct
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::EmergencyExit {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
         assert_eq!(
-            holder_response.index,
-            Decimal256::from_ratio(Uint128::new(1000000), Uint128::new(100))
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "staker2".to_string(),
+                amount: vec![
+                    Coin {
+                        denom: "rewards".to_string(),
+                        amount: Uint128::new(100),
+                    },
+                    Coin {
+                        denom: "staked".to_string(),
+                        amount: Uint128::new(100),
+                    },
+                ],
+            })
         );
-        // query state
-        let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
-        let state_response: StateResponse = from_binary(&res).unwrap();
 
-        assert_eq!(state_response.total_rewards, Uint128::new(1000000));
-        assert_eq!(state_response.rewards_claimed, Uint128::new(1000000));
-
-        // try to receive rewards again
         let info = mock_info("staker1", &[]);
-        let msg = ExecuteMsg::ReceiveReward {};
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(res, ContractError::NoRewards {});
+        let msg = ExecuteMsg::EmergencyExit {};
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoBond {});
     }
 
     #[test]
-    pub fn test_withdraw() {
+    pub fn test_pause() {
         let mut deps = mock_dependencies_with_balance(&[]);
-        let init_msg = default_init();
-        let env = mock_env();
-
+        let mut init_msg = default_init();
+        init_msg.pauser = Some("pauser".to_string());
+        init_msg.unbonding_period = Some(Duration::Time(100));
+        init_msg.allow_compounding = true;
+        let mut env = mock_env();
         instantiate(
             deps.as_mut(),
             env.clone(),
@@ -628,30 +3678,56 @@ mod tests {
         )
         .unwrap();
 
-        //first bond
-        let info = mock_info(
+        // bond staker1
+        let bond_info = mock_info(
             "staker1",
-            &vec![Coin {
+            &[Coin {
                 denom: "staked".to_string(),
                 amount: Uint128::new(100),
             }],
         );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), bond_info.clone(), msg).unwrap();
 
-        let msg = ExecuteMsg::BondStake {};
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        // random can't pause
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::Pause {
+            operations: vec![Operation::Bond],
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
 
-        //second bond
-        let info = mock_info(
-            "staker2",
-            &vec![Coin {
-                denom: "staked".to_string(),
-                amount: Uint128::new(200),
-            }],
+        // pauser pauses everything but withdrawals
+        let info = mock_info("pauser", &[]);
+        let msg = ExecuteMsg::Pause {
+            operations: vec![
+                Operation::Bond,
+                Operation::Claim,
+                Operation::UpdateReward,
+                Operation::Transfer,
+                Operation::UpdateHolder,
+            ],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let config_response: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config_response.pauser, Some("pauser".to_string()));
+        assert_eq!(
+            config_response.paused,
+            vec![
+                Operation::Bond,
+                Operation::Claim,
+                Operation::UpdateReward,
+                Operation::Transfer,
+                Operation::UpdateHolder,
+            ]
         );
-        let msg = ExecuteMsg::BondStake {};
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
 
-        //update reward
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let res = execute(deps.as_mut(), env.clone(), bond_info.clone(), msg).unwrap_err();
+        assert_eq!(res, ContractError::Paused {});
+
         let info = mock_info(
             "creator",
             &[Coin {
@@ -660,75 +3736,265 @@ mod tests {
             }],
         );
         let msg = ExecuteMsg::UpdateReward {};
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Paused {});
 
-        //withdraw staker1's stake without cap
-        let _info: MessageInfo = mock_info("staker1", &[]);
-        let _msg = ExecuteMsg::WithdrawStake { amount: None };
-        let res = execute(deps.as_mut(), env.clone(), _info.clone(), _msg).unwrap();
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Paused {});
+
+        // holders can't be updated outside of withdrawals
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::UpdateHolderReward {
+            address: Some("staker1".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Paused {});
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::SetAutoCompound { enabled: true };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Paused {});
+
+        // withdrawals stay open, unbonded stake can be claimed while reward claims are paused
+        let info = mock_info("staker1", &[]);
+        let msg = ExecuteMsg::WithdrawStake {
+            amount: Some(Uint128::new(50)),
+            owner: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(100);
+        let info = mock_info("staker1", &[]);
+        let res = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Claim {}).unwrap();
         assert_eq!(
             res.messages[0].msg,
             CosmosMsg::Bank(BankMsg::Send {
                 to_address: "staker1".to_string(),
                 amount: vec![Coin {
-                    denom: "rewards".to_string(),
-                    amount: Uint128::new(33),
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(50),
                 }],
-            }),
+            })
+        );
+
+        // admin unpauses bonding
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Unpause {
+            operations: vec![Operation::Bond],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), bond_info, msg).unwrap();
+
+        // pauser can't update the pauser
+        let info = mock_info("pauser", &[]);
+        let msg = ExecuteMsg::UpdatePauser { address: None };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // admin removes the pauser
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::UpdatePauser { address: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("pauser", &[]);
+        let msg = ExecuteMsg::Unpause {
+            operations: vec![Operation::Claim],
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    pub fn test_migrate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // populate a v0.1 store
+        v0_1::CONFIG
+            .save(
+                deps.as_mut().storage,
+                &v0_1::Config {
+                    staked_token_denom: "staked".to_string(),
+                    reward_denom: "rewards".to_string(),
+                    admin: Addr::unchecked("creator"),
+                },
+            )
+            .unwrap();
+        v0_1::STATE
+            .save(
+                deps.as_mut().storage,
+                &v0_1::State {
+                    global_index: Decimal256::one(),
+                    total_staked: Uint128::new(300),
+                    total_rewards: Uint128::new(400),
+                    rewards_claimed: Uint128::new(100),
+                },
+            )
+            .unwrap();
+        v0_1::HOLDERS
+            .save(
+                deps.as_mut().storage,
+                &Addr::unchecked("staker1"),
+                &v0_1::Holder {
+                    balance: Uint128::new(100),
+                    index: Decimal256::one(),
+                    dec_rewards: Decimal256::zero(),
+                    pending_rewards: Uint128::new(100),
+                },
+            )
+            .unwrap();
+        v0_1::HOLDERS
+            .save(
+                deps.as_mut().storage,
+                &Addr::unchecked("staker2"),
+                &v0_1::Holder {
+                    balance: Uint128::new(200),
+                    index: Decimal256::zero(),
+                    dec_rewards: Decimal256::zero(),
+                    pending_rewards: Uint128::zero(),
+                },
+            )
+            .unwrap();
+
+        let migrate_msg = MigrateMsg {
+            unbonding_period: Some(Duration::Time(100)),
+            reward_duration: None,
+            pauser: Some("pauser".to_string()),
+        };
+        let res = migrate(
+            deps.as_mut(),
+            env.clone(),
+            MigrateMsg {
+                reward_duration: Some(u64::MAX),
+                ..migrate_msg.clone()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::InvalidRewardDuration {});
+
+        let res = migrate(deps.as_mut(), env.clone(), migrate_msg.clone()).unwrap();
+        assert_eq!(res.attributes[1].value, "0.1.0");
+        assert_eq!(
+            cw2::get_contract_version(deps.as_ref().storage)
+                .unwrap()
+                .version,
+            env!("CARGO_PKG_VERSION")
         );
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let config_response: ConfigResponse = from_binary(&res).unwrap();
         assert_eq!(
-            res.messages[1].msg,
-            CosmosMsg::Bank(BankMsg::Send {
-                to_address: "staker1".to_string(),
-                amount: vec![Coin {
-                    denom: "staked".to_string(),
-                    amount: Uint128::new(100),
-                }],
-            }),
+            config_response,
+            ConfigResponse {
+                staked_token_denom: Denom::Native("staked".to_string()),
+                admin: Some("creator".to_string()),
+                pending_admin: None,
+                pending_admin_expiry: None,
+                unbonding_period: Some(Duration::Time(100)),
+                reward_duration: None,
+                pauser: Some("pauser".to_string()),
+                paused: vec![],
+                allow_compounding: false,
+                transfers_enabled: false,
+            }
+        );
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Dao {}).unwrap();
+        assert_eq!(
+            from_binary::<Addr>(&res).unwrap(),
+            Addr::unchecked("creator")
         );
 
-        //check state for total staked
         let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
-        let state: StateResponse = from_binary(&res).unwrap();
-        assert_eq!(state.total_staked, Uint128::new(200));
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.total_staked, Uint128::new(300));
+        assert_eq!(
+            state_response.rewards[0].denom,
+            Denom::Native("rewards".to_string())
+        );
+        assert_eq!(state_response.rewards[0].global_index, index(1, 1));
+        assert_eq!(state_response.rewards[0].total_rewards, Uint128::new(400));
+        assert_eq!(state_response.rewards[0].rewards_claimed, Uint128::new(100));
 
-        //withdraw staker2's stake with cap
-        let _info: MessageInfo = mock_info("staker2", &[]);
-        let _msg = ExecuteMsg::WithdrawStake {
-            amount: Some(Uint128::new(100)),
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Holder {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let holder_response: HolderResponse = from_binary(&res).unwrap();
+        assert_eq!(holder_response.balance, Uint128::new(100));
+        assert_eq!(
+            holder_response.rewards,
+            vec![HolderReward {
+                denom: Denom::Native("rewards".to_string()),
+                index: index(1, 1),
+                dec_rewards: Uint256::zero(),
+                pending_rewards: Uint128::new(100),
+            }]
+        );
+
+        // staker2 receives the rewards accrued before the migration
+        let info = mock_info("staker2", &[]);
+        let msg = ExecuteMsg::ReceiveReward {
+            recipient: None,
+            call: None,
+            owner: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), _info.clone(), _msg).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
         assert_eq!(
             res.messages[0].msg,
             CosmosMsg::Bank(BankMsg::Send {
                 to_address: "staker2".to_string(),
                 amount: vec![Coin {
                     denom: "rewards".to_string(),
-                    amount: Uint128::new(66),
+                    amount: Uint128::new(200),
                 }],
-            }),
+            })
         );
-        assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send {
-            to_address: "staker2".to_string(),
-            amount: vec![Coin {
-                denom: "staked".to_string(),
-                amount: Uint128::new(100),
-            }],
-        }));
 
-        //check state for total staked
+        // migrating the same version again keeps the store
+        let _res = migrate(deps.as_mut(), env.clone(), migrate_msg.clone()).unwrap();
         let res = query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap();
-        let state: StateResponse = from_binary(&res).unwrap();
-        assert_eq!(state.total_staked, Uint128::new(100));
+        let state_response: StateResponse = from_binary(&res).unwrap();
+        assert_eq!(state_response.rewards[0].rewards_claimed, Uint128::new(300));
 
+        // newer versions can't be downgraded
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:cw-share", "9.9.9").unwrap();
+        let res = migrate(deps.as_mut(), env.clone(), migrate_msg.clone()).unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::CannotDowngrade {
+                previous_version: "9.9.9".to_string(),
+                new_version: env!("CARGO_PKG_VERSION").to_string(),
+            }
+        );
 
+        // other contracts can't be migrated
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:other", "0.1.0").unwrap();
+        let res = migrate(deps.as_mut(), env, migrate_msg).unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::CannotMigrate {
+                previous_contract: "crates.io:other".to_string(),
+            }
+        );
     }
 
     #[test]
     pub fn test_update_admin() {
         let mut deps = mock_dependencies_with_balance(&[]);
         let init_msg = default_init();
-        let env = mock_env();
+        let mut env = mock_env();
 
         instantiate(
             deps.as_mut(),
@@ -737,22 +4003,234 @@ mod tests {
             init_msg,
         )
         .unwrap();
-        //random can't update admin
+        //random can't propose admin
         let info = mock_info("random", &[]);
-        let msg = ExecuteMsg::UpdateAdmin {
+        let msg = ExecuteMsg::ProposeAdmin {
             address: "new_admin".to_string(),
+            expiry: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
         assert_eq!(res, ContractError::Unauthorized {});
 
-        // admin can update admin
+        // nothing to accept or cancel before a proposal
+        let info = mock_info("new_admin", &[]);
+        let msg = ExecuteMsg::AcceptAdmin {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoPendingAdmin {});
+
         let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::UpdateAdmin {
+        let msg = ExecuteMsg::CancelAdminProposal {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::NoPendingAdmin {});
+
+        // expired proposal is refused
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ProposeAdmin {
             address: "new_admin".to_string(),
+            expiry: Some(Expiration::AtHeight(env.block.height)),
         };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::AdminProposalExpired {});
+
+        // admin proposes a typo and cancels it
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ProposeAdmin {
+            address: "new_admni".to_string(),
+            expiry: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::CancelAdminProposal {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // admin proposes new admin
+        let expiry = Expiration::AtHeight(env.block.height + 10);
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ProposeAdmin {
+            address: "new_admin".to_string(),
+            expiry: Some(expiry),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let config_response: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config_response.admin, Some("creator".to_string()));
+        assert_eq!(config_response.pending_admin, Some("new_admin".to_string()));
+        assert_eq!(config_response.pending_admin_expiry, Some(expiry));
+
+        // only the pending admin can accept
+        let info = mock_info("random", &[]);
+        let msg = ExecuteMsg::AcceptAdmin {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // proposal can't be accepted after the expiry
+        let mut expired_env = env.clone();
+        expired_env.block.height += 10;
+        let info = mock_info("new_admin", &[]);
+        let msg = ExecuteMsg::AcceptAdmin {};
+        let res = execute(deps.as_mut(), expired_env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::AdminProposalExpired {});
+
+        // pending admin accepts
+        env.block.height += 1;
+        let info = mock_info("new_admin", &[]);
+        let msg = ExecuteMsg::AcceptAdmin {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
         assert_eq!(res.attributes[1].value, "new_admin".to_string());
-        
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let config_response: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config_response.admin, Some("new_admin".to_string()));
+        assert_eq!(config_response.pending_admin, None);
+
+        // old admin lost control
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::RenounceAdmin {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // new admin renounces
+        let info = mock_info("new_admin", &[]);
+        let msg = ExecuteMsg::RenounceAdmin {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+        let config_response: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config_response.admin, None);
+
+        let info = mock_info("new_admin", &[]);
+        let msg = ExecuteMsg::ProposeAdmin {
+            address: "creator".to_string(),
+            expiry: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    pub fn test_reward_overflow() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_init(),
+        )
+        .unwrap();
+
+        // bond staker1
+        let info = mock_info(
+            "staker1",
+            &[Coin {
+                denom: "staked".to_string(),
+                amount: Uint128::new(1),
+            }],
+        );
+        let msg = ExecuteMsg::BondStake { recipient: None };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::MAX,
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // rewards that don't fit the pool are refused instead of dropped
+        let info = mock_info(
+            "creator",
+            &[Coin {
+                denom: "rewards".to_string(),
+                amount: Uint128::new(1),
+            }],
+        );
+        let msg = ExecuteMsg::UpdateReward {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert!(matches!(res, ContractError::Overflow(_)));
+
+        let res = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::AccruedRewards {
+                address: "staker1".to_string(),
+            },
+        )
+        .unwrap();
+        let accrued: AccruedRewardsResponse = from_binary(&res).unwrap();
+        assert_eq!(accrued.rewards[0].claimable, Uint128::MAX);
     }
 
-}   
\ No newline at end of file
+    proptest! {
+        #[test]
+        fn prop_claimed_rewards_never_exceed_deposits(
+            stakes in vec(1u128..1_000_000_000_000, 1..6),
+            deposits in vec((1u128..1_000_000_000_000, 0u64..200), 1..10),
+            reward_duration in option::of(1u64..100),
+        ) {
+            let mut deps = mock_dependencies();
+            let mut env = mock_env();
+            let mut init_msg = default_init();
+            init_msg.reward_duration = reward_duration;
+            instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg).unwrap();
+
+            let stakers: Vec<String> = (0..stakes.len()).map(|i| format!("staker{}", i)).collect();
+            for (staker, stake) in stakers.iter().zip(stakes.iter()) {
+                let info = mock_info(staker, &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(*stake),
+                }]);
+                execute(deps.as_mut(), env.clone(), info, ExecuteMsg::BondStake { recipient: None }).unwrap();
+            }
+
+            // deposits are interleaved with time passing and stake changes
+            let mut deposited = 0u128;
+            for (i, (amount, seconds)) in deposits.iter().enumerate() {
+                let info = mock_info("creator", &[Coin {
+                    denom: "rewards".to_string(),
+                    amount: Uint128::new(*amount),
+                }]);
+                execute(deps.as_mut(), env.clone(), info, ExecuteMsg::UpdateReward {}).unwrap();
+                deposited += amount;
+
+                env.block.time = env.block.time.plus_seconds(*seconds);
+                let staker = &stakers[i % stakers.len()];
+                let info = mock_info(staker, &[Coin {
+                    denom: "staked".to_string(),
+                    amount: Uint128::new(*amount % 1000 + 1),
+                }]);
+                execute(deps.as_mut(), env.clone(), info, ExecuteMsg::BondStake { recipient: None }).unwrap();
+            }
+
+            // every stream is over
+            env.block.time = env.block.time.plus_seconds(100);
+            let mut claimed = 0u128;
+            for staker in stakers.iter() {
+                let res = execute(
+                    deps.as_mut(),
+                    env.clone(),
+                    mock_info(staker, &[]),
+                    ExecuteMsg::ReceiveReward {
+                        recipient: None,
+                        call: None,
+                        owner: None,
+                    },
+                );
+                if let Ok(res) = res {
+                    if let CosmosMsg::Bank(BankMsg::Send { amount, .. }) = &res.messages[0].msg {
+                        claimed += amount[0].amount.u128();
+                    }
+                }
+            }
+
+            // only dust below one token per holder and one for the index is left
+            prop_assert!(claimed <= deposited);
+            prop_assert!(deposited - claimed <= stakers.len() as u128 + 1);
+        }
+    }
+}